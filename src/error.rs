@@ -0,0 +1,135 @@
+use crate::inference::InferenceError;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// the error of this crate, it covers all stages of the solutions.
+/// the inner errors can be got by ```std::error::Error::source```.
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// inference error (such as set input, compute or get output)
+    Inference(InferenceError),
+
+    /// the model cannot be loaded
+    ModelLoad {
+        /// the model path, or the model name if not loaded from a file
+        path: PathBuf,
+        source: InferenceError,
+    },
+
+    /// the sha256 of the model file is not the known good value
+    ModelHashMismatch {
+        path: PathBuf,
+        expect: String,
+        got: String,
+    },
+
+    /// the input cannot be converted to tensors
+    Preprocess(String),
+
+    /// the outputs cannot be converted to results
+    Postprocess(String),
+
+    /// the solution options or the model are not matched
+    Config(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// create a ```ModelLoad``` error with the model path
+    #[inline]
+    pub fn model_load(path: impl Into<PathBuf>, source: InferenceError) -> Self {
+        Self::ModelLoad {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl From<InferenceError> for Error {
+    #[inline(always)]
+    fn from(value: InferenceError) -> Self {
+        Self::Inference(value)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Inference(e) => Some(e),
+            Self::ModelLoad { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Inference(_) => write!(f, "inference failed"),
+            Self::ModelLoad { path, .. } => write!(f, "cannot load model `{}`", path.display()),
+            Self::ModelHashMismatch { path, expect, got } => write!(
+                f,
+                "model `{}` sha256 mismatch: expect {}, got {}",
+                path.display(),
+                expect,
+                got
+            ),
+            Self::Preprocess(msg) => write!(f, "preprocess failed: {}", msg),
+            Self::Postprocess(msg) => write!(f, "postprocess failed: {}", msg),
+            Self::Config(msg) => write!(f, "invalid config: {}", msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as _;
+
+    /// the messages of the sources of ```e```
+    fn source_chain(e: &Error) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut source = e.source();
+        while let Some(s) = source {
+            chain.push(s.to_string());
+            source = s.source();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_error_source_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let path = "./assets/not_exists.tflite";
+        let e = Error::model_load(path, InferenceError::io_error(path, io));
+        assert_eq!(
+            e.to_string(),
+            "cannot load model `./assets/not_exists.tflite`"
+        );
+
+        let chain = source_chain(&e);
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[1], "no such file");
+
+        // the wasi-nn errno is kept in the chain
+        let source = InferenceError::WasiNnError {
+            errno: 1,
+            source: Some(Box::new(InferenceError::InvalidArgument)),
+        };
+        let e = Error::model_load("face.tflite", source);
+        assert_eq!(e.to_string(), "cannot load model `face.tflite`");
+        assert_eq!(
+            source_chain(&e),
+            ["wasi-nn error (errno 1)", "invalid argument"]
+        );
+
+        // the inference error is only in the chain, not in the message
+        let e = Error::from(InferenceError::WasiNnError {
+            errno: 5,
+            source: None,
+        });
+        assert_eq!(e.to_string(), "inference failed");
+        assert_eq!(source_chain(&e), ["wasi-nn error (errno 5)"]);
+    }
+}
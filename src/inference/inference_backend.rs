@@ -0,0 +1,138 @@
+use super::{InferenceError, InferenceGraphDevice, InferenceGraphEncoding, InferenceTensor};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// handle of a graph which is loaded by a backend
+pub type InferenceGraphHandle = u32;
+
+/// handle of a graph execution context which is created by a backend
+pub type InferenceExecutionContextHandle = u32;
+
+/// Inference Backend
+/// the runtime which really loads the graph and does inference.
+/// the handles are opaque for the caller, every backend can use its own numbering.
+///
+/// [`WasiNnBackend`](super::WasiNnBackend) is the default backend (only for ```wasm32``` target),
+/// other runtimes (or mock backends for tests) can be used with [`InferenceGraphBuilder::backend`](super::InferenceGraphBuilder::backend).
+pub trait InferenceBackend: Debug + Send + Sync {
+    /// load a graph from builders (the model content)
+    fn load(
+        &self,
+        builders: &[&[u8]],
+        encoding: InferenceGraphEncoding,
+        device: InferenceGraphDevice,
+    ) -> Result<InferenceGraphHandle, InferenceError>;
+
+    /// load a graph which is registered in the backend by name (such as WasmEdge ```--nn-preload```).
+    /// default return ```InvalidArgument``` (for the backends which cannot load by name).
+    #[inline(always)]
+    fn load_by_name(&self, _name: &str) -> Result<InferenceGraphHandle, InferenceError> {
+        Err(InferenceError::InvalidArgument)
+    }
+
+    /// create a new execution context for the graph
+    fn init_execution_context(
+        &self,
+        graph: InferenceGraphHandle,
+    ) -> Result<InferenceExecutionContextHandle, InferenceError>;
+
+    /// set the input tensor with index for the execution context
+    fn set_input(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        tensor: &InferenceTensor,
+    ) -> Result<(), InferenceError>;
+
+    /// do inference
+    fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError>;
+
+    /// copy the output with index to ```buf```, return the bytes number copied.
+    fn get_output(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        buf: &mut [u8],
+    ) -> Result<u32, InferenceError>;
+
+    /// release the graph, it is called when the ```InferenceGraph``` is dropped.
+    /// default do nothing (for the backends which cannot release graphs).
+    #[inline(always)]
+    fn drop_graph(&self, _graph: InferenceGraphHandle) -> Result<(), InferenceError> {
+        Ok(())
+    }
+
+    /// release the execution context, it is called when the ```InferenceGraphExecutor``` is dropped.
+    /// default do nothing (for the backends which cannot release execution contexts).
+    #[inline(always)]
+    fn drop_execution_context(
+        &self,
+        _ctx: InferenceExecutionContextHandle,
+    ) -> Result<(), InferenceError> {
+        Ok(())
+    }
+}
+
+/// a shared backend can be used by many graphs (e.g. to check the backend state in tests)
+impl<B: InferenceBackend + ?Sized> InferenceBackend for Arc<B> {
+    #[inline(always)]
+    fn load(
+        &self,
+        builders: &[&[u8]],
+        encoding: InferenceGraphEncoding,
+        device: InferenceGraphDevice,
+    ) -> Result<InferenceGraphHandle, InferenceError> {
+        self.as_ref().load(builders, encoding, device)
+    }
+
+    #[inline(always)]
+    fn load_by_name(&self, name: &str) -> Result<InferenceGraphHandle, InferenceError> {
+        self.as_ref().load_by_name(name)
+    }
+
+    #[inline(always)]
+    fn init_execution_context(
+        &self,
+        graph: InferenceGraphHandle,
+    ) -> Result<InferenceExecutionContextHandle, InferenceError> {
+        self.as_ref().init_execution_context(graph)
+    }
+
+    #[inline(always)]
+    fn set_input(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        tensor: &InferenceTensor,
+    ) -> Result<(), InferenceError> {
+        self.as_ref().set_input(ctx, index, tensor)
+    }
+
+    #[inline(always)]
+    fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError> {
+        self.as_ref().compute(ctx)
+    }
+
+    #[inline(always)]
+    fn get_output(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        buf: &mut [u8],
+    ) -> Result<u32, InferenceError> {
+        self.as_ref().get_output(ctx, index, buf)
+    }
+
+    #[inline(always)]
+    fn drop_graph(&self, graph: InferenceGraphHandle) -> Result<(), InferenceError> {
+        self.as_ref().drop_graph(graph)
+    }
+
+    #[inline(always)]
+    fn drop_execution_context(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+    ) -> Result<(), InferenceError> {
+        self.as_ref().drop_execution_context(ctx)
+    }
+}
@@ -1,13 +1,20 @@
 use std::fmt::{Display, Formatter};
-use wasi_nn;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Clone, Debug)]
 pub enum InferenceError {
-    /// graph file error
-    IOError,
+    /// graph file (or other file used by the backends) error
+    IOError {
+        /// the file which is read or written, None if unknown
+        path: Option<PathBuf>,
+        source: Arc<std::io::Error>,
+    },
 
     /// get output buf error
     OutputGetLenError {
+        /// the output index
+        index: u32,
         expect: u32,
         got: u32,
     },
@@ -22,28 +29,110 @@ pub enum InferenceError {
 
     RuntimeError,
 
+    /// no backend can be used to load the graph
+    BackendNotFound,
+
+    /// the wasi-nn errno, ```source``` is the corresponding error if the errno is known
+    /// (such as ```InvalidArgument```)
+    WasiNnError {
+        errno: u16,
+        source: Option<Box<InferenceError>>,
+    },
+
     UnknownError,
 }
 
-impl From<wasi_nn::NnErrno> for InferenceError {
-    #[inline(always)]
-    fn from(value: wasi_nn::NnErrno) -> Self {
-        match value {
-            wasi_nn::NN_ERRNO_INVALID_ARGUMENT => Self::InvalidArgument,
-            wasi_nn::NN_ERRNO_INVALID_ENCODING => Self::InvalidEncoding,
-            wasi_nn::NN_ERRNO_MISSING_MEMORY => Self::MissingMemory,
-            wasi_nn::NN_ERRNO_BUSY => Self::Busy,
-            wasi_nn::NN_ERRNO_RUNTIME_ERROR => Self::RuntimeError,
-            _ => Self::UnknownError,
+impl InferenceError {
+    /// create an ```IOError``` with the file path
+    #[inline]
+    pub fn io_error(path: impl AsRef<Path>, source: std::io::Error) -> Self {
+        Self::IOError {
+            path: Some(path.as_ref().to_path_buf()),
+            source: Arc::new(source),
         }
     }
 }
 
-impl std::error::Error for InferenceError {}
+impl From<std::io::Error> for InferenceError {
+    #[inline]
+    fn from(value: std::io::Error) -> Self {
+        Self::IOError {
+            path: None,
+            source: Arc::new(value),
+        }
+    }
+}
+
+impl std::error::Error for InferenceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IOError { source, .. } => Some(source.as_ref()),
+            Self::WasiNnError {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl Display for InferenceError {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        // todo: impl the error description, or use ```this_error``` crate
-        unimplemented!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IOError {
+                path: Some(path), ..
+            } => write!(f, "io error on file `{}`", path.display()),
+            Self::IOError { path: None, .. } => write!(f, "io error"),
+            Self::OutputGetLenError { index, expect, got } => write!(
+                f,
+                "output {} length mismatch: expect {} bytes, got {} bytes",
+                index, expect, got
+            ),
+            Self::InvalidEncoding => write!(f, "invalid graph encoding"),
+            Self::InvalidArgument => write!(f, "invalid argument"),
+            Self::MissingMemory => write!(f, "missing memory"),
+            Self::Busy => write!(f, "the backend is busy"),
+            Self::RuntimeError => write!(f, "backend runtime error"),
+            Self::BackendNotFound => write!(f, "no inference backend can be used"),
+            Self::WasiNnError { errno, .. } => write!(f, "wasi-nn error (errno {})", errno),
+            Self::UnknownError => write!(f, "unknown inference error"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_error_display_and_source() {
+        let e = InferenceError::io_error(
+            "./assets/not_exists.tflite",
+            std::io::Error::new(std::io::ErrorKind::NotFound, "not found"),
+        );
+        assert_eq!(
+            e.to_string(),
+            "io error on file `./assets/not_exists.tflite`"
+        );
+        assert_eq!(e.source().unwrap().to_string(), "not found");
+
+        let e = InferenceError::OutputGetLenError {
+            index: 1,
+            expect: 8,
+            got: 4,
+        };
+        assert_eq!(
+            e.to_string(),
+            "output 1 length mismatch: expect 8 bytes, got 4 bytes"
+        );
+        assert!(e.source().is_none());
+
+        let e = InferenceError::WasiNnError {
+            errno: 1,
+            source: Some(Box::new(InferenceError::InvalidArgument)),
+        };
+        assert_eq!(e.to_string(), "wasi-nn error (errno 1)");
+        assert_eq!(e.source().unwrap().to_string(), "invalid argument");
     }
 }
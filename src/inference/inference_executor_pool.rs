@@ -0,0 +1,321 @@
+use super::{InferenceError, InferenceGraph, InferenceGraphExecutor};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Executor Pool
+/// a bounded pool of executors of one graph, the executors are created when needed
+/// and reused after they are returned, so the execution contexts are not initialized every time.
+///
+/// the pool is a shared handle, clone is cheap and all clones use the same executors.
+///
+/// ### Examples
+///
+/// ```no_run
+/// use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, InferenceTensor, InferenceTensorDataLayout, InferenceTensorType};
+///
+/// let graph = InferenceGraphBuilder::default().build_from_file("./module.tflite")?;
+/// let pool = graph.new_executor_pool(4);
+/// // wait until an executor is free, the executor returns to the pool when dropped
+/// let mut executor = pool.acquire()?;
+/// let input = InferenceTensor::new(InferenceTensorType::F32, InferenceTensorDataLayout::NHWC, Vec::default(), Vec::default());
+/// executor.set_inputs_and_run([(0, input)])?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
+/// ```
+#[derive(Clone)]
+pub struct InferenceGraphExecutorPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    graph: InferenceGraph,
+    max_size: usize,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+#[derive(Default)]
+struct PoolState {
+    idle: Vec<InferenceGraphExecutor>,
+    /// the number of executors which are created (idle + in use)
+    created: usize,
+    /// the number of created executors which are warmed up, it is counted when they are returned
+    ready: usize,
+}
+
+impl InferenceGraphExecutorPool {
+    /// create a pool with at most ```max_size``` executors, 0 is same as 1.
+    pub fn new(graph: InferenceGraph, max_size: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                graph,
+                max_size: max_size.max(1),
+                state: Mutex::new(PoolState::default()),
+                returned: Condvar::new(),
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn graph(&self) -> &InferenceGraph {
+        &self.inner.graph
+    }
+
+    #[inline(always)]
+    pub fn max_size(&self) -> usize {
+        self.inner.max_size
+    }
+
+    /// the number of executors which are created and not in use
+    #[inline]
+    pub fn idle_count(&self) -> usize {
+        self.inner.state.lock().unwrap().idle.len()
+    }
+
+    /// warm up the idle executors and the free slots of the pool (the executors are created),
+    /// see [`InferenceGraphExecutor::warmup`]. it does not wait for the executors in use,
+    /// so the pool is not ready until it is called again after they are returned.
+    pub fn warmup(&self, n: usize) -> Result<(), InferenceError> {
+        let mut executors = Vec::with_capacity(self.inner.max_size);
+        while let Some(executor) = self.try_acquire()? {
+            executors.push(executor);
+        }
+        for executor in executors.iter_mut() {
+            executor.warmup(n)?;
+        }
+        Ok(())
+    }
+
+    /// whether all executors of the pool are created and warmed up
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.inner.state.lock().unwrap().ready == self.inner.max_size
+    }
+
+    /// get an executor, blocking until one is returned if all executors are in use.
+    pub fn acquire(&self) -> Result<PooledExecutor, InferenceError> {
+        let mut state = self.inner.state.lock().unwrap();
+        loop {
+            if let Some(executor) = state.idle.pop() {
+                return Ok(self.pooled(executor));
+            }
+            if state.created < self.inner.max_size {
+                state.created += 1;
+                drop(state);
+                return self.create();
+            }
+            state = self.inner.returned.wait(state).unwrap();
+        }
+    }
+
+    /// get an executor without blocking, return ```None``` if all executors are in use.
+    pub fn try_acquire(&self) -> Result<Option<PooledExecutor>, InferenceError> {
+        let mut state = self.inner.state.lock().unwrap();
+        if let Some(executor) = state.idle.pop() {
+            return Ok(Some(self.pooled(executor)));
+        }
+        if state.created < self.inner.max_size {
+            state.created += 1;
+            drop(state);
+            return self.create().map(Some);
+        }
+        Ok(None)
+    }
+
+    /// create a new executor, the slot is already reserved in ```created```
+    fn create(&self) -> Result<PooledExecutor, InferenceError> {
+        match self.inner.graph.new_graph_executor() {
+            Ok(executor) => Ok(self.pooled(executor)),
+            Err(e) => {
+                // release the slot, so other waiters can try again
+                self.inner.state.lock().unwrap().created -= 1;
+                self.inner.returned.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn pooled(&self, executor: InferenceGraphExecutor) -> PooledExecutor {
+        PooledExecutor {
+            was_ready: executor.is_ready(),
+            executor: Some(executor),
+            pool: self.inner.clone(),
+        }
+    }
+}
+
+impl InferenceGraph {
+    /// create a pool of executors for this graph, see [`InferenceGraphExecutorPool`]
+    #[inline]
+    pub fn new_executor_pool(&self, max_size: usize) -> InferenceGraphExecutorPool {
+        InferenceGraphExecutorPool::new(self.clone(), max_size)
+    }
+}
+
+/// an executor which is checked out from the pool, it returns to the pool when dropped.
+pub struct PooledExecutor {
+    executor: Option<InferenceGraphExecutor>,
+    pool: Arc<PoolInner>,
+    /// whether the executor is warmed up when it is checked out
+    was_ready: bool,
+}
+
+impl Deref for PooledExecutor {
+    type Target = InferenceGraphExecutor;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        self.executor.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledExecutor {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.executor.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledExecutor {
+    fn drop(&mut self) {
+        if let Some(executor) = self.executor.take() {
+            let mut state = self.pool.state.lock().unwrap();
+            if executor.is_ready() && !self.was_ready {
+                state.ready += 1;
+            }
+            state.idle.push(executor);
+            drop(state);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inference::tflite_model::test_model::build_tflite_model;
+    use crate::inference::{
+        InferenceGraphBuilder, InferenceTensorInfo, InferenceTensorType, ReplayBackend,
+    };
+    use std::time::Duration;
+
+    fn pool(backend: Arc<ReplayBackend>, max_size: usize) -> InferenceGraphExecutorPool {
+        InferenceGraphBuilder::default()
+            .backend(backend)
+            .build_from_bytes(vec![0])
+            .unwrap()
+            .new_executor_pool(max_size)
+    }
+
+    #[test]
+    fn test_pool_reuse_executors() {
+        let backend = Arc::new(ReplayBackend::new());
+        let pool = pool(backend.clone(), 2);
+        assert_eq!(backend.live_execution_contexts(), 0);
+
+        let first = pool.acquire().unwrap();
+        let second = pool.try_acquire().unwrap().unwrap();
+        assert!(pool.try_acquire().unwrap().is_none());
+        assert_eq!(backend.live_execution_contexts(), 2);
+
+        drop(first);
+        assert_eq!(pool.idle_count(), 1);
+        let third = pool.try_acquire().unwrap().unwrap();
+        // the returned executor is reused
+        assert_eq!(backend.live_execution_contexts(), 2);
+        assert_eq!(pool.idle_count(), 0);
+
+        drop(second);
+        drop(third);
+        drop(pool);
+        assert_eq!(backend.live_execution_contexts(), 0);
+        assert_eq!(backend.live_graphs(), 0);
+    }
+
+    #[test]
+    fn test_pool_acquire_blocking() {
+        let backend = Arc::new(ReplayBackend::new());
+        let pool = pool(backend.clone(), 1);
+        let executor = pool.acquire().unwrap();
+
+        let waiter = {
+            let pool = pool.clone();
+            std::thread::spawn(move || pool.acquire().is_ok())
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+
+        drop(executor);
+        assert!(waiter.join().unwrap());
+        assert_eq!(backend.live_execution_contexts(), 1);
+    }
+
+    #[test]
+    fn test_pool_warmup() {
+        let info = InferenceTensorInfo {
+            name: String::new(),
+            shape: vec![1, 1],
+            tp: InferenceTensorType::U8,
+            quantization: None,
+            dynamic_batch: false,
+        };
+        let model = build_tflite_model(std::slice::from_ref(&info), std::slice::from_ref(&info));
+        let backend = Arc::new(
+            ReplayBackend::new()
+                .push_outputs(vec![vec![0]])
+                .push_outputs(vec![vec![0]]),
+        );
+        let pool = InferenceGraphBuilder::default()
+            .backend(backend.clone())
+            .build_from_bytes(model)
+            .unwrap()
+            .new_executor_pool(2);
+        assert!(!pool.is_ready());
+        pool.warmup(1).unwrap();
+        assert!(pool.is_ready());
+        assert_eq!(pool.idle_count(), 2);
+        assert_eq!(backend.remaining(), 0);
+        assert!(pool.acquire().unwrap().is_ready());
+    }
+
+    #[test]
+    fn test_pool_warmup_with_executor_in_use() {
+        let info = InferenceTensorInfo {
+            name: String::new(),
+            shape: vec![1, 1],
+            tp: InferenceTensorType::U8,
+            quantization: None,
+            dynamic_batch: false,
+        };
+        let model = build_tflite_model(std::slice::from_ref(&info), std::slice::from_ref(&info));
+        let backend =
+            Arc::new((0..3).fold(ReplayBackend::new(), |b, _| b.push_outputs(vec![vec![0]])));
+        let pool = InferenceGraphBuilder::default()
+            .backend(backend.clone())
+            .build_from_bytes(model)
+            .unwrap()
+            .new_executor_pool(2);
+
+        // the executor in use is skipped instead of waiting for it
+        let executor = pool.acquire().unwrap();
+        pool.warmup(1).unwrap();
+        assert_eq!(backend.remaining(), 2);
+        assert_eq!(pool.idle_count(), 1);
+        assert!(!pool.is_ready());
+
+        drop(executor);
+        assert!(!pool.is_ready());
+        pool.warmup(1).unwrap();
+        assert!(pool.is_ready());
+        assert_eq!(backend.remaining(), 0);
+    }
+
+    #[test]
+    fn test_pool_size_zero() {
+        let backend = Arc::new(ReplayBackend::new());
+        let pool = pool(backend, 0);
+        assert_eq!(pool.max_size(), 1);
+        let _executor = pool.acquire().unwrap();
+        assert!(pool.try_acquire().unwrap().is_none());
+    }
+}
@@ -1,10 +1,35 @@
-use super::{InferenceError, InferenceTensor};
+use super::{
+    tflite_model, InferenceBackend, InferenceError, InferenceExecutionContextHandle,
+    InferenceGraphHandle, InferenceTensor, InferenceTensorDataLayout, InferenceTensorElement,
+    InferenceTensorInfo,
+};
+use crate::profiler::Profiler;
+use std::borrow::Cow;
 use std::path::Path;
-use wasi_nn;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+static LIVE_GRAPHS: AtomicUsize = AtomicUsize::new(0);
+static LIVE_GRAPH_EXECUTORS: AtomicUsize = AtomicUsize::new(0);
+
+/// the number of ```InferenceGraph``` which are not dropped (can be used to detect leaks)
+#[inline(always)]
+pub fn live_graph_count() -> usize {
+    LIVE_GRAPHS.load(Ordering::SeqCst)
+}
+
+/// the number of ```InferenceGraphExecutor``` which are not dropped (can be used to detect leaks)
+#[inline(always)]
+pub fn live_graph_executor_count() -> usize {
+    LIVE_GRAPH_EXECUTORS.load(Ordering::SeqCst)
+}
 
 /// Inference Graph Encoding
 /// with graph encoding, we can chose the backend for wasi-nn
 /// now, it can only support tflite!
+///
+/// the encodings of the newer wasi-nn hosts are enabled by the cargo feature
+/// ```wasi-nn-extra-encodings```.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum InferenceGraphEncoding {
     Onnx,
@@ -12,19 +37,12 @@ pub enum InferenceGraphEncoding {
     Tensorflow,
     Pytorch,
     TensorflowLite,
-}
-
-impl Into<wasi_nn::GraphEncoding> for InferenceGraphEncoding {
-    #[inline(always)]
-    fn into(self) -> wasi_nn::GraphEncoding {
-        match self {
-            InferenceGraphEncoding::Onnx => wasi_nn::GRAPH_ENCODING_ONNX,
-            InferenceGraphEncoding::Openvino => wasi_nn::GRAPH_ENCODING_OPENVINO,
-            InferenceGraphEncoding::Tensorflow => wasi_nn::GRAPH_ENCODING_TENSORFLOW,
-            InferenceGraphEncoding::Pytorch => wasi_nn::GRAPH_ENCODING_PYTORCH,
-            InferenceGraphEncoding::TensorflowLite => unsafe { std::mem::transmute(4u8) },
-        }
-    }
+    /// let the host detect the encoding from the model content
+    #[cfg(feature = "wasi-nn-extra-encodings")]
+    Autodetect,
+    /// llama.cpp models (such as ```.gguf``` files)
+    #[cfg(feature = "wasi-nn-extra-encodings")]
+    Ggml,
 }
 
 /// Inference Graph Device
@@ -37,31 +55,39 @@ pub enum InferenceGraphDevice {
     TPU,
 }
 
-impl Into<wasi_nn::ExecutionTarget> for InferenceGraphDevice {
-    #[inline]
-    fn into(self) -> wasi_nn::ExecutionTarget {
-        match self {
-            InferenceGraphDevice::CPU => wasi_nn::EXECUTION_TARGET_CPU,
-            InferenceGraphDevice::GPU => wasi_nn::EXECUTION_TARGET_GPU,
-            InferenceGraphDevice::TPU => wasi_nn::EXECUTION_TARGET_TPU,
-        }
-    }
-}
-
 /// builder for InferenceGraph
 ///
 /// ### Examples
 ///
-/// #### build a graph with default config ( tflite + cpu )
-/// ```
+/// #### build a graph with default config ( tflite + cpu + wasi-nn backend )
+/// ```no_run
 /// use mediapipe_wasinn_demo::inference::InferenceGraphBuilder;
 /// let path = "./module.tflite";
 /// let graph = InferenceGraphBuilder::default().build_from_file(path)?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
+/// ```
+///
+/// #### build a OpenVINO graph from multi files
+/// ```no_run
+/// use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, InferenceGraphEncoding};
+/// let graph = InferenceGraphBuilder::default()
+///     .encoding(InferenceGraphEncoding::Openvino)
+///     .build_from_files(["./model.xml", "./model.bin"])?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
+/// ```
+///
+/// #### build a graph with a custom backend
+/// ```no_run
+/// use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, ReplayBackend};
+/// let path = "./module.tflite";
+/// let graph = InferenceGraphBuilder::default().backend(ReplayBackend::new()).build_from_file(path)?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
 /// ```
 #[derive(Debug, Clone)]
 pub struct InferenceGraphBuilder {
     encoding: InferenceGraphEncoding,
     device: InferenceGraphDevice,
+    backend: Option<Arc<dyn InferenceBackend>>,
 }
 
 impl Default for InferenceGraphBuilder {
@@ -81,6 +107,7 @@ impl InferenceGraphBuilder {
         Self {
             encoding: backend,
             device,
+            backend: None,
         }
     }
 
@@ -96,62 +123,227 @@ impl InferenceGraphBuilder {
         self
     }
 
-    // todo: new interface to support array input for ```load```
-
+    /// set the backend to load the graph and do inference.
+    /// if no backend is set, the default backend (wasi-nn) will be used.
     #[inline]
+    pub fn backend(mut self, backend: impl InferenceBackend + 'static) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// the default backend: wasi-nn for ```wasm32``` target, no default backend for other targets.
+    #[inline(always)]
+    fn default_backend() -> Option<Arc<dyn InferenceBackend>> {
+        #[cfg(target_arch = "wasm32")]
+        return Some(Arc::new(super::WasiNnBackend));
+        #[cfg(not(target_arch = "wasm32"))]
+        return None;
+    }
+
+    #[inline(always)]
     pub fn build_from_bytes(self, bytes: Vec<u8>) -> Result<InferenceGraph, InferenceError> {
-        let build_info = self.clone();
-        let graph_handle =
-            unsafe { wasi_nn::load(&[bytes.as_ref()], self.encoding.into(), self.device.into()) }
-                .map_err(|e| InferenceError::from(e))?;
-        Ok(InferenceGraph {
-            build_info,
-            graph_handle,
-            _graph_content: bytes,
-        })
+        self.build_from_parts(vec![bytes])
+    }
+
+    /// build the graph from several parts of the model,
+    /// such as OpenVINO ```.xml``` + ```.bin```, the order of parts is defined by the backend.
+    #[inline]
+    pub fn build_from_parts(self, parts: Vec<Vec<u8>>) -> Result<InferenceGraph, InferenceError> {
+        self.build_from_contents(parts.into_iter().map(Cow::Owned).collect())
+    }
+
+    /// build the graph from a static model, such as a model embedded by ```include_bytes!```.
+    /// the model content is not copied.
+    #[inline]
+    pub fn build_from_static_bytes(
+        self,
+        bytes: &'static [u8],
+    ) -> Result<InferenceGraph, InferenceError> {
+        self.build_from_contents(vec![Cow::Borrowed(bytes)])
+    }
+
+    fn build_from_contents(
+        self,
+        parts: Vec<Cow<'static, [u8]>>,
+    ) -> Result<InferenceGraph, InferenceError> {
+        let backend = self.get_backend()?;
+        // read the model inputs and outputs information (only tflite now).
+        // the information is optional, if the model cannot be read, the graph has no information
+        // and the backend decides whether the model is valid.
+        let (inputs, outputs) = match parts.as_slice() {
+            [model] if is_io_readable(&self.encoding, &parts) => {
+                tflite_model::read_tflite_io(model)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+            }
+            _ => Default::default(),
+        };
+
+        let builders = parts.iter().map(|p| p.as_ref()).collect::<Vec<&[u8]>>();
+        let graph_handle = backend.load(&builders, self.encoding.clone(), self.device.clone())?;
+        Ok(self.new_graph(backend, graph_handle, inputs, outputs, parts))
+    }
+
+    /// build the graph which is registered in the host by name,
+    /// such as the models preloaded by WasmEdge ```--nn-preload name:GGML:AUTO:model.gguf```.
+    ///
+    /// the model content is not read, so the inputs and outputs information of the graph is empty.
+    /// the wasi-nn backend supports it with the cargo feature ```wasi-nn-load-by-name```
+    /// (otherwise it returns ```InvalidArgument```), and the host must support ```load_by_name```,
+    /// otherwise the wasm module cannot be instantiated.
+    pub fn build_from_name(self, name: &str) -> Result<InferenceGraph, InferenceError> {
+        let backend = self.get_backend()?;
+        let graph_handle = backend.load_by_name(name)?;
+        Ok(self.new_graph(backend, graph_handle, Vec::new(), Vec::new(), Vec::new()))
+    }
+
+    #[inline(always)]
+    fn get_backend(&self) -> Result<Arc<dyn InferenceBackend>, InferenceError> {
+        self.backend
+            .clone()
+            .or_else(Self::default_backend)
+            .ok_or(InferenceError::BackendNotFound)
+    }
+
+    #[inline]
+    fn new_graph(
+        self,
+        backend: Arc<dyn InferenceBackend>,
+        graph_handle: InferenceGraphHandle,
+        inputs: Vec<InferenceTensorInfo>,
+        outputs: Vec<InferenceTensorInfo>,
+        parts: Vec<Cow<'static, [u8]>>,
+    ) -> InferenceGraph {
+        LIVE_GRAPHS.fetch_add(1, Ordering::SeqCst);
+        InferenceGraph {
+            inner: Arc::new(InferenceGraphInner {
+                build_info: self,
+                backend,
+                graph_handle,
+                inputs,
+                outputs,
+                graph_content: parts,
+            }),
+        }
     }
 
     #[inline(always)]
     pub fn build_from_file(self, file: impl AsRef<Path>) -> Result<InferenceGraph, InferenceError> {
-        let bytes = std::fs::read(file).map_err(|_e| InferenceError::IOError)?;
-        self.build_from_bytes(bytes)
+        self.build_from_files([file])
+    }
+
+    /// build the graph from several files, the order of files is the order of parts.
+    /// see [`InferenceGraphBuilder::build_from_parts`]
+    #[inline]
+    pub fn build_from_files(
+        self,
+        files: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<InferenceGraph, InferenceError> {
+        let mut parts = Vec::new();
+        for file in files {
+            let file = file.as_ref();
+            parts.push(std::fs::read(file).map_err(|e| InferenceError::io_error(file, e))?);
+        }
+        self.build_from_parts(parts)
     }
 }
 
 /// InferenceGraph
 /// InferenceGraph can create a InferenceGraphExecutor which can do inference.
 ///
+/// the graph is a shared handle, clone is cheap and all clones use the same loaded graph.
+/// the graph is released when the last clone and all executors are dropped.
+#[derive(Clone)]
 pub struct InferenceGraph {
+    inner: Arc<InferenceGraphInner>,
+}
+
+struct InferenceGraphInner {
     build_info: InferenceGraphBuilder,
-    graph_handle: wasi_nn::Graph,
-    _graph_content: Vec<u8>,
+    backend: Arc<dyn InferenceBackend>,
+    graph_handle: InferenceGraphHandle,
+    inputs: Vec<InferenceTensorInfo>,
+    outputs: Vec<InferenceTensorInfo>,
+    graph_content: Vec<Cow<'static, [u8]>>,
+}
+
+/// whether the inputs and outputs information can be read from the model content,
+/// now only a single tflite model can be read
+#[inline]
+fn is_io_readable(encoding: &InferenceGraphEncoding, parts: &[Cow<'static, [u8]>]) -> bool {
+    *encoding == InferenceGraphEncoding::TensorflowLite && parts.len() == 1
 }
 
 impl InferenceGraph {
     #[inline(always)]
     pub fn encoding(&self) -> InferenceGraphEncoding {
-        self.build_info.encoding.clone()
+        self.inner.build_info.encoding.clone()
     }
 
     #[inline(always)]
     pub fn device(&self) -> InferenceGraphDevice {
-        self.build_info.device.clone()
+        self.inner.build_info.device.clone()
+    }
+
+    /// the inputs information of the graph, it is empty if the model format cannot be read.
+    /// now only the tflite model can be read.
+    #[inline(always)]
+    pub fn inputs(&self) -> &[InferenceTensorInfo] {
+        &self.inner.inputs
+    }
+
+    /// the outputs information of the graph, it is empty if the model format cannot be read.
+    /// now only the tflite model can be read.
+    #[inline(always)]
+    pub fn outputs(&self) -> &[InferenceTensorInfo] {
+        &self.inner.outputs
+    }
+
+    /// whether the inputs and outputs information is read from the model content,
+    /// if it is true and the information is empty, the model is corrupt or not supported.
+    /// the graphs of other encodings and the graphs built by name are not read.
+    #[inline]
+    pub(crate) fn is_io_readable(&self) -> bool {
+        is_io_readable(&self.inner.build_info.encoding, &self.inner.graph_content)
+    }
+
+    #[inline(always)]
+    pub fn backend(&self) -> &dyn InferenceBackend {
+        self.inner.backend.as_ref()
+    }
+
+    /// run the graph ```n``` times with zero inputs in a new executor, see [`InferenceGraphExecutor::warmup`].
+    /// it does the setup of the graph (such as the delegates) in the backend, but the execution
+    /// context is released after warmup, use the executor's (or the pool's) ```warmup``` to keep it.
+    #[inline]
+    pub fn warmup(&self, n: usize) -> Result<(), InferenceError> {
+        self.new_graph_executor()?.warmup(n)
     }
 
+    /// create a new executor, the executor holds the graph so it can outlive this handle.
     #[inline]
     pub fn new_graph_executor(&self) -> Result<InferenceGraphExecutor, InferenceError> {
-        let ctx = unsafe { wasi_nn::init_execution_context(self.graph_handle) }
-            .map_err(|e| InferenceError::from(e))?;
+        let ctx = self
+            .inner
+            .backend
+            .init_execution_context(self.inner.graph_handle)?;
+        LIVE_GRAPH_EXECUTORS.fetch_add(1, Ordering::SeqCst);
         Ok(InferenceGraphExecutor {
-            graph: self,
+            graph: self.clone(),
             execute_ctx: ctx,
+            output_buf: Vec::new(),
+            profiler: Profiler::default(),
+            ready: false,
         })
     }
 }
 
-impl Drop for InferenceGraph {
+impl Drop for InferenceGraphInner {
     fn drop(&mut self) {
-        // todo: destroy this graph?
+        // the error cannot be handled when dropping
+        let _ = self.backend.drop_graph(self.graph_handle);
+        LIVE_GRAPHS.fetch_sub(1, Ordering::SeqCst);
     }
 }
 
@@ -161,7 +353,7 @@ impl Drop for InferenceGraph {
 /// ### Examples
 ///
 /// #### load module and do inference
-/// ```
+/// ```no_run
 /// use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, InferenceTensor, InferenceTensorDataLayout, InferenceTensorType};
 ///
 /// let path = "./module.tflite";
@@ -170,34 +362,60 @@ impl Drop for InferenceGraph {
 /// // generate input tensor
 /// let input = InferenceTensor::new(InferenceTensorType::F32,InferenceTensorDataLayout::NHWC,Vec::default(),Vec::default());
 /// executor.set_inputs_and_run([(0, input)])?;
-/// let (output, output_size) = executor.get_output(0, 1024)?;
+/// let output = executor.get_output::<f32>(0)?;
+/// let output_data = output.to_vec::<f32>()?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
 /// ```
-pub struct InferenceGraphExecutor<'a> {
-    graph: &'a InferenceGraph,
-    execute_ctx: wasi_nn::GraphExecutionContext,
+///
+/// the executor owns a handle of its graph, so it can be stored or moved to another thread.
+pub struct InferenceGraphExecutor {
+    graph: InferenceGraph,
+    execute_ctx: InferenceExecutionContextHandle,
+    /// the reusable buffer to get the raw outputs
+    output_buf: Vec<u8>,
+    /// records ```set_input```, ```compute``` and ```get_output```
+    profiler: Profiler,
+    /// set after a successful warmup
+    ready: bool,
 }
 
-impl<'a> InferenceGraphExecutor<'a> {
+impl InferenceGraphExecutor {
     #[inline]
-    pub fn graph(&self) -> &'a InferenceGraph {
-        self.graph
+    pub fn graph(&self) -> &InferenceGraph {
+        &self.graph
+    }
+
+    /// the profiler which records the backend calls, it is disabled by default
+    #[inline]
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    #[inline]
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = profiler;
+    }
+
+    /// whether this executor is warmed up, see [`InferenceGraphExecutor::warmup`]
+    #[inline(always)]
+    pub fn is_ready(&self) -> bool {
+        self.ready
     }
 
-    pub fn set_inputs_and_run(
+    pub fn set_inputs_and_run<'t>(
         &mut self,
-        inputs: impl AsRef<[(u32, InferenceTensor<'a>)]>,
+        inputs: impl AsRef<[(u32, InferenceTensor<'t>)]>,
     ) -> Result<(), InferenceError> {
+        let scope = self.profiler.scope("set_input");
         for (ref index, ref input) in inputs.as_ref() {
-            let (shape, data) = input.tensor_data_ref();
-            let tensor = wasi_nn::Tensor {
-                dimensions: shape,
-                type_: input.tp().into(),
-                data,
-            };
-            unsafe { wasi_nn::set_input(self.execute_ctx, *index, tensor) }
-                .map_err(|e| InferenceError::from(e))?;
+            self.graph
+                .inner
+                .backend
+                .set_input(self.execute_ctx, *index, input)?;
         }
+        drop(scope);
 
+        let _scope = self.profiler.scope("compute");
         let res = self.run();
 
         // drop inputs after run
@@ -205,9 +423,40 @@ impl<'a> InferenceGraphExecutor<'a> {
         res
     }
 
+    /// run ```n``` times with zero inputs of the shapes in the graph inputs information,
+    /// so the slow first ```compute``` (such as delegate setup and allocations) is done before
+    /// the real inputs. this executor is marked ready after success.
+    ///
+    /// return ```InvalidArgument``` if ```n``` is 0 or the graph has no inputs information.
+    pub fn warmup(&mut self, n: usize) -> Result<(), InferenceError> {
+        if n == 0 || self.graph.inputs().is_empty() {
+            return Err(InferenceError::InvalidArgument);
+        }
+        let inputs = self
+            .graph
+            .inputs()
+            .iter()
+            .enumerate()
+            .map(|(index, info)| {
+                let input = InferenceTensor::new(
+                    info.tp.clone(),
+                    info.data_layout(),
+                    info.shape.clone(),
+                    vec![0; info.byte_len()],
+                );
+                (index as u32, input)
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..n {
+            self.set_inputs_and_run(&inputs)?;
+        }
+        self.ready = true;
+        Ok(())
+    }
+
     #[inline(always)]
     fn run(&mut self) -> Result<(), InferenceError> {
-        unsafe { wasi_nn::compute(self.execute_ctx) }.map_err(|e| InferenceError::from(e))
+        self.graph.inner.backend.compute(self.execute_ctx)
     }
 
     pub fn get_output_with_buffer(
@@ -216,36 +465,211 @@ impl<'a> InferenceGraphExecutor<'a> {
         mut buf: impl AsMut<[u8]>,
         buf_size: u32,
     ) -> Result<u32, InferenceError> {
-        unsafe {
-            wasi_nn::get_output(
-                self.execute_ctx,
-                index,
-                buf.as_mut() as *mut [u8] as *mut u8,
-                buf_size,
-            )
-        }
-        .map_err(|e| InferenceError::from(e))
+        let buf = buf.as_mut();
+        let buf_size = std::cmp::min(buf_size as usize, buf.len());
+        self.read_output(index, &mut buf[..buf_size])
     }
 
-    // todo: opt these api
+    /// read the output to ```buf```, it only borrows ```self``` (and its graph information)
+    /// immutably, so it can be called while the outputs information is borrowed.
+    #[inline]
+    fn read_output(&self, index: u32, buf: &mut [u8]) -> Result<u32, InferenceError> {
+        let _scope = self.profiler.scope("get_output");
+        self.graph
+            .inner
+            .backend
+            .get_output(self.execute_ctx, index, buf)
+    }
 
-    pub fn get_output_u8(
-        &mut self,
-        index: u32,
-        expect_len: u32,
-    ) -> Result<Vec<u8>, InferenceError> {
+    /// read the output with the expected length, see ```get_output_u8```
+    fn read_output_u8(&self, index: u32, expect_len: u32) -> Result<Vec<u8>, InferenceError> {
         let mut buf: Vec<u8> = vec![0; expect_len as usize];
-        let recv = self.get_output_with_buffer(index, &mut buf[..], expect_len)?;
+        let recv = self.read_output(index, &mut buf)?;
         if recv == expect_len {
             Ok(buf)
         } else {
             Err(InferenceError::OutputGetLenError {
+                index,
                 expect: expect_len,
                 got: recv,
             })
         }
     }
 
+    /// the initial buffer size to get output if the graph has no outputs information
+    const OUTPUT_INITIAL_BUF_SIZE: usize = 4096;
+    /// the max buffer size to get output if the output size is unknown
+    const OUTPUT_MAX_BUF_SIZE: usize = 64 << 20;
+
+    /// get the output with index as an owned tensor.
+    ///
+    /// if the graph has the outputs information (such as tflite model), the shape and type
+    /// are from the information, and the type must be same as ```T```.
+    /// the batch of a dynamic batch output is the received size divided by the size of one batch.
+    /// otherwise, the buffer will grow until the output can be got, the shape is
+    /// ```[elements count]``` and the layout is ```Unknown```.
+    ///
+    /// some backends (such as WasmEdge tflite) copy at most the buffer size without error,
+    /// so a full buffer is retried with a larger one. return ```MissingMemory``` if the output
+    /// is still truncated by the max buffer size (64MB).
+    pub fn get_output<T: InferenceTensorElement>(
+        &mut self,
+        index: u32,
+    ) -> Result<InferenceTensor<'static>, InferenceError> {
+        if let Some(info) = self.graph.outputs().get(index as usize) {
+            if info.tp != T::TP {
+                return Err(InferenceError::InvalidArgument);
+            }
+            let expect_len = info.byte_len() as u32;
+            if !info.dynamic_batch {
+                let data = self.read_output_u8(index, expect_len)?;
+                return Ok(InferenceTensor::new(
+                    T::TP,
+                    info.data_layout(),
+                    info.shape.clone(),
+                    data,
+                ));
+            }
+
+            let data = self.read_output_growing(index, expect_len as usize)?;
+            if expect_len == 0 || data.is_empty() || data.len() % expect_len as usize != 0 {
+                return Err(InferenceError::OutputGetLenError {
+                    index,
+                    expect: expect_len,
+                    got: data.len() as u32,
+                });
+            }
+            let mut shape = info.shape.clone();
+            if let Some(batch) = shape.first_mut() {
+                *batch = (data.len() / expect_len as usize) as u32;
+            }
+            return Ok(InferenceTensor::new(T::TP, info.data_layout(), shape, data));
+        }
+
+        let buf = self.read_output_growing(index, Self::OUTPUT_INITIAL_BUF_SIZE)?;
+        if buf.len() % T::TP.byte_size() != 0 {
+            return Err(InferenceError::OutputGetLenError {
+                index,
+                expect: (buf.len() - buf.len() % T::TP.byte_size()) as u32,
+                got: buf.len() as u32,
+            });
+        }
+        let shape = vec![(buf.len() / T::TP.byte_size()) as u32];
+        Ok(InferenceTensor::new(
+            T::TP,
+            InferenceTensorDataLayout::Unknown,
+            shape,
+            buf,
+        ))
+    }
+
+    /// read the output with a buffer of ```initial_len``` bytes, the buffer grows
+    /// until the output is not truncated, see ```get_output```
+    fn read_output_growing(
+        &self,
+        index: u32,
+        initial_len: usize,
+    ) -> Result<Vec<u8>, InferenceError> {
+        let mut buf = vec![0u8; initial_len.clamp(1, Self::OUTPUT_MAX_BUF_SIZE)];
+        loop {
+            let buf_size = buf.len() as u32;
+            match self.read_output(index, &mut buf) {
+                // the output may be truncated to the buffer size
+                Ok(recv) if recv == buf_size => {
+                    if buf.len() >= Self::OUTPUT_MAX_BUF_SIZE {
+                        return Err(InferenceError::MissingMemory);
+                    }
+                    buf.resize(buf.len() << 1, 0);
+                }
+                Ok(recv) => {
+                    buf.truncate(recv as usize);
+                    return Ok(buf);
+                }
+                // the buffer is too small (for the backends which report errors).
+                // ```InvalidArgument``` is not retried, it is also the error of an invalid index.
+                Err(InferenceError::OutputGetLenError { .. })
+                    if buf.len() < Self::OUTPUT_MAX_BUF_SIZE =>
+                {
+                    buf.resize(buf.len() << 1, 0);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// get the output with index as f32 values, the quantized output is dequantized
+    /// using the quantization parameters in the graph outputs information.
+    /// if the graph has no outputs information, the output must be f32.
+    #[inline]
+    pub fn get_output_dequantized(&mut self, index: u32) -> Result<Vec<f32>, InferenceError> {
+        self.get_output_dequantized_batch(index, 1)
+    }
+
+    /// same as ```get_output_dequantized```, but the inputs are a batch of ```batch_size```,
+    /// so the output size is ```batch_size``` times of the size in the outputs information.
+    pub fn get_output_dequantized_batch(
+        &mut self,
+        index: u32,
+        batch_size: u32,
+    ) -> Result<Vec<f32>, InferenceError> {
+        match self.graph.outputs().get(index as usize) {
+            Some(info) => {
+                let mut shape = info.shape.clone();
+                if let Some(batch) = shape.first_mut() {
+                    *batch *= batch_size;
+                }
+                let data = self.read_output_u8(index, info.byte_len() as u32 * batch_size)?;
+                InferenceTensor::new(info.tp.clone(), info.data_layout(), shape, data)
+                    .dequantize(info.quantization.as_ref())
+            }
+            None => self.get_output::<f32>(index)?.to_vec::<f32>(),
+        }
+    }
+
+    /// same as ```get_output_dequantized```, but the values are written to ```out``` (cleared first).
+    /// the raw outputs are read to a buffer owned by the executor, so if the graph has
+    /// the outputs information, no memory is allocated after the first call.
+    pub fn get_output_dequantized_into(
+        &mut self,
+        index: u32,
+        out: &mut Vec<f32>,
+    ) -> Result<(), InferenceError> {
+        let info = match self.graph.outputs().get(index as usize) {
+            Some(info) => info,
+            None => {
+                out.clear();
+                out.extend(self.get_output::<f32>(index)?.to_vec::<f32>()?);
+                return Ok(());
+            }
+        };
+
+        let expect_len = info.byte_len() as u32;
+        let mut buf = std::mem::take(&mut self.output_buf);
+        buf.resize(expect_len as usize, 0);
+        let res = match self.read_output(index, &mut buf) {
+            Ok(recv) if recv == expect_len => {
+                InferenceTensor::new_ref(info.tp.clone(), info.data_layout(), &info.shape, &buf)
+                    .dequantize_into(info.quantization.as_ref(), out)
+            }
+            Ok(recv) => Err(InferenceError::OutputGetLenError {
+                index,
+                expect: expect_len,
+                got: recv,
+            }),
+            Err(e) => Err(e),
+        };
+        self.output_buf = buf;
+        res
+    }
+
+    pub fn get_output_u8(
+        &mut self,
+        index: u32,
+        expect_len: u32,
+    ) -> Result<Vec<u8>, InferenceError> {
+        self.read_output_u8(index, expect_len)
+    }
+
     pub fn get_output_f32(
         &mut self,
         index: u32,
@@ -253,20 +677,16 @@ impl<'a> InferenceGraphExecutor<'a> {
     ) -> Result<Vec<f32>, InferenceError> {
         let mut buf: Vec<f32> = vec![0f32; expect_len as usize];
         let expect_buf_len = expect_len << 2;
-        let recv = unsafe {
-            wasi_nn::get_output(
-                self.execute_ctx,
-                index,
-                &mut buf[..] as *mut [f32] as *mut u8,
-                expect_buf_len,
-            )
-        }
-        .map_err(|e| InferenceError::from(e))?;
+        let buf_u8 = unsafe {
+            std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, expect_buf_len as usize)
+        };
+        let recv = self.get_output_with_buffer(index, buf_u8, expect_buf_len)?;
 
         if recv == expect_buf_len {
             Ok(buf)
         } else {
             Err(InferenceError::OutputGetLenError {
+                index,
                 expect: expect_buf_len,
                 got: recv,
             })
@@ -274,15 +694,86 @@ impl<'a> InferenceGraphExecutor<'a> {
     }
 }
 
-impl<'a> Drop for InferenceGraphExecutor<'a> {
+impl Drop for InferenceGraphExecutor {
     fn drop(&mut self) {
-        // todo: destroy this graph execution context?
+        // the error cannot be handled when dropping
+        let _ = self
+            .graph
+            .inner
+            .backend
+            .drop_execution_context(self.execute_ctx);
+        LIVE_GRAPH_EXECUTORS.fetch_sub(1, Ordering::SeqCst);
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::inference::tflite_model::test_model::build_tflite_model;
+    use crate::inference::{InferenceTensorDataLayout, InferenceTensorInfo, InferenceTensorType};
+    use std::sync::Mutex;
+
+    /// a backend which copies the input 0 to output 0 when compute.
+    /// the output is truncated to the buffer size without error, same as WasmEdge tflite.
+    /// the other outputs are ```InvalidArgument```.
+    #[derive(Debug, Default)]
+    struct EchoBackend {
+        contexts: Mutex<Vec<(Vec<u8>, Vec<u8>)>>,
+        get_output_calls: AtomicUsize,
+    }
+
+    impl InferenceBackend for EchoBackend {
+        fn load(
+            &self,
+            builders: &[&[u8]],
+            _encoding: InferenceGraphEncoding,
+            _device: InferenceGraphDevice,
+        ) -> Result<InferenceGraphHandle, InferenceError> {
+            Ok(builders.len() as InferenceGraphHandle)
+        }
+
+        fn init_execution_context(
+            &self,
+            _graph: InferenceGraphHandle,
+        ) -> Result<InferenceExecutionContextHandle, InferenceError> {
+            let mut contexts = self.contexts.lock().unwrap();
+            contexts.push(Default::default());
+            Ok((contexts.len() - 1) as InferenceExecutionContextHandle)
+        }
+
+        fn set_input(
+            &self,
+            ctx: InferenceExecutionContextHandle,
+            _index: u32,
+            tensor: &InferenceTensor,
+        ) -> Result<(), InferenceError> {
+            self.contexts.lock().unwrap()[ctx as usize].0 = tensor.data_ref().to_vec();
+            Ok(())
+        }
+
+        fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError> {
+            let mut contexts = self.contexts.lock().unwrap();
+            let (input, output) = &mut contexts[ctx as usize];
+            *output = input.clone();
+            Ok(())
+        }
+
+        fn get_output(
+            &self,
+            ctx: InferenceExecutionContextHandle,
+            index: u32,
+            buf: &mut [u8],
+        ) -> Result<u32, InferenceError> {
+            self.get_output_calls.fetch_add(1, Ordering::SeqCst);
+            if index != 0 {
+                return Err(InferenceError::InvalidArgument);
+            }
+            let output = &self.contexts.lock().unwrap()[ctx as usize].1;
+            let len = std::cmp::min(output.len(), buf.len());
+            buf[..len].copy_from_slice(&output[..len]);
+            Ok(len as u32)
+        }
+    }
 
     #[test]
     fn test_graph_builder_default() {
@@ -290,4 +781,201 @@ mod test {
         assert_eq!(graph.device, InferenceGraphDevice::CPU);
         assert_eq!(graph.encoding, InferenceGraphEncoding::TensorflowLite);
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_graph_builder_without_backend() {
+        let res = InferenceGraphBuilder::default().build_from_bytes(vec![0; 4]);
+        assert!(matches!(res, Err(InferenceError::BackendNotFound)));
+    }
+
+    #[test]
+    fn test_graph_executor_with_backend() {
+        let graph = InferenceGraphBuilder::default()
+            .backend(EchoBackend::default())
+            .build_from_bytes(vec![0; 4])
+            .unwrap();
+        // not a tflite model, so the graph has no inputs and outputs information
+        assert!(graph.inputs().is_empty() && graph.outputs().is_empty());
+        let mut executor = graph.new_graph_executor().unwrap();
+
+        let input = InferenceTensor::new(
+            InferenceTensorType::U8,
+            InferenceTensorDataLayout::NHWC,
+            vec![1, 2, 2, 1],
+            vec![1, 2, 3, 4],
+        );
+        executor.set_inputs_and_run([(0, input)]).unwrap();
+        assert_eq!(executor.get_output_u8(0, 4).unwrap(), vec![1, 2, 3, 4]);
+        assert!(matches!(
+            executor.get_output_u8(0, 8),
+            Err(InferenceError::OutputGetLenError {
+                index: 0,
+                expect: 8,
+                got: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_graph_executor_get_output() {
+        let graph = InferenceGraphBuilder::default()
+            .backend(EchoBackend::default())
+            .build_from_bytes(vec![0; 4])
+            .unwrap();
+        let mut executor = graph.new_graph_executor().unwrap();
+
+        let data = (0..5000).map(|i| i as f32).collect::<Vec<f32>>();
+        let input =
+            InferenceTensor::from_elements(InferenceTensorDataLayout::NHWC, vec![1, 5000], &data);
+        executor.set_inputs_and_run([(0, input)]).unwrap();
+        // no outputs information, so the shape is [elements count]
+        let output = executor.get_output::<f32>(0).unwrap();
+        assert_eq!(output.shape_ref(), &[5000]);
+        assert_eq!(output.data_layout(), &InferenceTensorDataLayout::Unknown);
+        assert_eq!(output.to_vec::<f32>().unwrap(), data);
+        assert!(output.to_vec::<i32>().is_err());
+
+        let mut out = vec![0f32; 3];
+        executor.get_output_dequantized_into(0, &mut out).unwrap();
+        assert_eq!(out, data);
+
+        // the output fills the initial buffer exactly, it is not treated as truncated
+        let data = vec![7u8; InferenceGraphExecutor::OUTPUT_INITIAL_BUF_SIZE];
+        let input = InferenceTensor::from_elements(
+            InferenceTensorDataLayout::NHWC,
+            vec![data.len() as u32],
+            &data,
+        );
+        executor.set_inputs_and_run([(0, input)]).unwrap();
+        let output = executor.get_output::<u8>(0).unwrap();
+        assert_eq!(output.to_vec::<u8>().unwrap(), data);
+    }
+
+    #[test]
+    fn test_graph_executor_get_invalid_output() {
+        let backend = Arc::new(EchoBackend::default());
+        let graph = InferenceGraphBuilder::default()
+            .backend(backend.clone())
+            .build_from_bytes(vec![0; 4])
+            .unwrap();
+        let mut executor = graph.new_graph_executor().unwrap();
+        let input =
+            InferenceTensor::from_elements(InferenceTensorDataLayout::NHWC, vec![1], &[1f32]);
+        executor.set_inputs_and_run([(0, input)]).unwrap();
+
+        // the invalid index is not retried with larger buffers
+        let calls = backend.get_output_calls.load(Ordering::SeqCst);
+        assert!(matches!(
+            executor.get_output::<f32>(1),
+            Err(InferenceError::InvalidArgument)
+        ));
+        assert_eq!(backend.get_output_calls.load(Ordering::SeqCst), calls + 1);
+    }
+
+    #[test]
+    fn test_graph_executor_get_dynamic_batch_output() {
+        let info = InferenceTensorInfo {
+            name: String::new(),
+            shape: vec![1, 2],
+            tp: InferenceTensorType::F32,
+            quantization: None,
+            dynamic_batch: true,
+        };
+        let model = build_tflite_model(std::slice::from_ref(&info), std::slice::from_ref(&info));
+        let graph = InferenceGraphBuilder::default()
+            .backend(EchoBackend::default())
+            .build_from_bytes(model)
+            .unwrap();
+        let mut executor = graph.new_graph_executor().unwrap();
+
+        for batch in [1u32, 3] {
+            let data = (0..batch * 2).map(|i| i as f32).collect::<Vec<f32>>();
+            let input = InferenceTensor::from_elements(
+                InferenceTensorDataLayout::NHWC,
+                vec![batch, 2],
+                &data,
+            );
+            executor.set_inputs_and_run([(0, input)]).unwrap();
+            let output = executor.get_output::<f32>(0).unwrap();
+            assert_eq!(output.shape_ref(), &[batch, 2]);
+            assert_eq!(output.to_vec::<f32>().unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_graph_executor_owns_graph() {
+        fn assert_send<T: Send + 'static>(_: &T) {}
+
+        let mut executor = InferenceGraphBuilder::default()
+            .backend(EchoBackend::default())
+            .build_from_bytes(vec![0; 4])
+            .unwrap()
+            .new_graph_executor()
+            .unwrap();
+        assert_send(&executor);
+        assert_eq!(
+            executor.graph().encoding(),
+            InferenceGraphEncoding::TensorflowLite
+        );
+
+        // the graph handle is dropped, the executor can still be moved to another thread
+        let output = std::thread::spawn(move || {
+            let input = InferenceTensor::from_elements(
+                InferenceTensorDataLayout::NHWC,
+                vec![1, 2],
+                &[1u8, 2],
+            );
+            executor.set_inputs_and_run([(0, input)]).unwrap();
+            executor.get_output_u8(0, 2).unwrap()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(output, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_graph_warmup() {
+        let info = InferenceTensorInfo {
+            name: String::new(),
+            shape: vec![1, 2, 2, 1],
+            tp: InferenceTensorType::F32,
+            quantization: None,
+            dynamic_batch: false,
+        };
+        let model = tflite_model::test_model::build_tflite_model(
+            std::slice::from_ref(&info),
+            std::slice::from_ref(&info),
+        );
+        let graph = InferenceGraphBuilder::default()
+            .backend(EchoBackend::default())
+            .build_from_bytes(model)
+            .unwrap();
+        let mut executor = graph.new_graph_executor().unwrap();
+        assert!(!executor.is_ready());
+        assert!(matches!(
+            executor.warmup(0),
+            Err(InferenceError::InvalidArgument)
+        ));
+        assert!(!executor.is_ready());
+        executor.warmup(2).unwrap();
+        assert!(executor.is_ready());
+        // the readiness is per executor
+        assert!(!graph.new_graph_executor().unwrap().is_ready());
+        // the zero inputs have the shape and type of the inputs information
+        assert_eq!(executor.get_output_u8(0, 16).unwrap(), vec![0; 16]);
+        let output = executor.get_output::<f32>(0).unwrap();
+        assert_eq!(output.shape_ref(), &[1, 2, 2, 1]);
+        assert_eq!(output.data_layout(), &InferenceTensorDataLayout::NHWC);
+
+        // no inputs information
+        let graph = InferenceGraphBuilder::default()
+            .backend(EchoBackend::default())
+            .build_from_bytes(vec![0; 4])
+            .unwrap();
+        assert!(matches!(
+            graph.warmup(1),
+            Err(InferenceError::InvalidArgument)
+        ));
+    }
 }
@@ -1,27 +1,139 @@
-use wasi_nn;
+use super::InferenceError;
+use half::f16;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum InferenceTensorDataLayout {
     NCHW,
     NHWC,
     CHWN,
+    /// the tensor is not an image or the layout is not known, such as the model output
+    /// ```[1, 896, 16]```. it cannot be transposed.
+    Unknown,
 }
 
-#[derive(Debug, Clone)]
+impl InferenceTensorDataLayout {
+    /// the axes names ordered by the layout
+    #[inline]
+    fn axes(&self) -> Option<[u8; 4]> {
+        match self {
+            InferenceTensorDataLayout::NCHW => Some(*b"NCHW"),
+            InferenceTensorDataLayout::NHWC => Some(*b"NHWC"),
+            InferenceTensorDataLayout::CHWN => Some(*b"CHWN"),
+            InferenceTensorDataLayout::Unknown => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum InferenceTensorType {
     F16,
     F32,
     U8,
     I32,
+    I8,
 }
 
-impl Into<wasi_nn::TensorType> for InferenceTensorType {
-    fn into(self) -> wasi_nn::TensorType {
+impl InferenceTensorType {
+    /// the bytes size of one element
+    #[inline]
+    pub fn byte_size(&self) -> usize {
         match self {
-            InferenceTensorType::F16 => wasi_nn::TENSOR_TYPE_F16,
-            InferenceTensorType::F32 => wasi_nn::TENSOR_TYPE_F32,
-            InferenceTensorType::U8 => wasi_nn::TENSOR_TYPE_U8,
-            InferenceTensorType::I32 => wasi_nn::TENSOR_TYPE_I32,
+            InferenceTensorType::F16 => 2,
+            InferenceTensorType::F32 => 4,
+            InferenceTensorType::U8 => 1,
+            InferenceTensorType::I32 => 4,
+            InferenceTensorType::I8 => 1,
+        }
+    }
+}
+
+/// the element type which can be stored in the tensor data
+pub trait InferenceTensorElement: Copy {
+    const TP: InferenceTensorType;
+
+    /// ```bytes.len()``` must be ```TP.byte_size()```
+    fn from_ne_bytes(bytes: &[u8]) -> Self;
+
+    fn extend_ne_bytes(self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_tensor_element {
+    ($t:ty, $tp:expr) => {
+        impl InferenceTensorElement for $t {
+            const TP: InferenceTensorType = $tp;
+
+            #[inline(always)]
+            fn from_ne_bytes(bytes: &[u8]) -> Self {
+                <$t>::from_ne_bytes(bytes.try_into().unwrap())
+            }
+
+            #[inline(always)]
+            fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_ne_bytes());
+            }
+        }
+    };
+}
+
+impl_tensor_element!(f16, InferenceTensorType::F16);
+impl_tensor_element!(f32, InferenceTensorType::F32);
+impl_tensor_element!(u8, InferenceTensorType::U8);
+impl_tensor_element!(i32, InferenceTensorType::I32);
+impl_tensor_element!(i8, InferenceTensorType::I8);
+
+/// Quantization parameters of a tensor
+/// real_value = scale * (quantized_value - zero_point)
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferenceTensorQuantization {
+    pub scale: f32,
+    pub zero_point: i64,
+}
+
+impl InferenceTensorQuantization {
+    /// quantize the real value, the result is not clamped to the range of quantized type
+    #[inline(always)]
+    pub fn quantize(&self, real_value: f32) -> i64 {
+        (real_value / self.scale).round() as i64 + self.zero_point
+    }
+
+    #[inline(always)]
+    pub fn dequantize(&self, quantized_value: i64) -> f32 {
+        self.scale * (quantized_value - self.zero_point) as f32
+    }
+}
+
+/// Tensor information of a graph input or output (such as read from the tflite model)
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferenceTensorInfo {
+    pub name: String,
+    pub shape: Vec<u32>,
+    pub tp: InferenceTensorType,
+    /// None if the tensor is not quantized
+    pub quantization: Option<InferenceTensorQuantization>,
+    /// the first dimension (batch) can be changed when running,
+    /// and the first dimension of ```shape``` is 1
+    pub dynamic_batch: bool,
+}
+
+impl InferenceTensorInfo {
+    /// the number of elements
+    #[inline]
+    pub fn elements_count(&self) -> usize {
+        self.shape.iter().map(|d| *d as usize).product()
+    }
+
+    /// the number of bytes of the tensor data
+    #[inline]
+    pub fn byte_len(&self) -> usize {
+        self.elements_count() * self.tp.byte_size()
+    }
+
+    /// the tensors with 4 dimensions are NHWC (the tflite convention), others are ```Unknown```
+    #[inline]
+    pub fn data_layout(&self) -> InferenceTensorDataLayout {
+        match self.shape.len() {
+            4 => InferenceTensorDataLayout::NHWC,
+            _ => InferenceTensorDataLayout::Unknown,
         }
     }
 }
@@ -73,6 +185,17 @@ impl<'a> InferenceTensor<'a> {
         }
     }
 
+    /// create a tensor from elements, the type is decided by the element type
+    pub fn from_elements<T: InferenceTensorElement>(
+        data_layout: InferenceTensorDataLayout,
+        shape: Vec<u32>,
+        elements: &[T],
+    ) -> Self {
+        let mut data = Vec::with_capacity(elements.len() * T::TP.byte_size());
+        elements.iter().for_each(|e| e.extend_ne_bytes(&mut data));
+        Self::new(T::TP, data_layout, shape, data)
+    }
+
     pub fn shape_ref(&self) -> &[u32] {
         match &self.data {
             &TensorData::Ref { ref shape_ref, .. } => shape_ref,
@@ -108,7 +231,338 @@ impl<'a> InferenceTensor<'a> {
         &self.data_layout
     }
 
+    /// the number of elements
+    #[inline]
+    pub fn elements_count(&self) -> usize {
+        self.data_ref().len() / self.tp.byte_size()
+    }
+
+    /// copy the tensor data to a vector, the element type must be same as the tensor type
+    pub fn to_vec<T: InferenceTensorElement>(&self) -> Result<Vec<T>, InferenceError> {
+        if T::TP != self.tp {
+            return Err(InferenceError::InvalidArgument);
+        }
+        Ok(self
+            .data_ref()
+            .chunks_exact(T::TP.byte_size())
+            .map(T::from_ne_bytes)
+            .collect())
+    }
+
+    /// convert the tensor data to f32 values.
+    /// for integer types, the values are dequantized if the quantization parameters are given.
+    pub fn dequantize(
+        &self,
+        quantization: Option<&InferenceTensorQuantization>,
+    ) -> Result<Vec<f32>, InferenceError> {
+        let mut out = Vec::with_capacity(self.elements_count());
+        self.dequantize_into(quantization, &mut out)?;
+        Ok(out)
+    }
+
+    /// same as ```dequantize```, but the values are written to ```out``` (cleared first),
+    /// so the buffer can be reused.
+    pub fn dequantize_into(
+        &self,
+        quantization: Option<&InferenceTensorQuantization>,
+        out: &mut Vec<f32>,
+    ) -> Result<(), InferenceError> {
+        fn values<'t, T: InferenceTensorElement + 't>(
+            tensor: &'t InferenceTensor,
+        ) -> Result<impl Iterator<Item = T> + 't, InferenceError> {
+            if T::TP != tensor.tp {
+                return Err(InferenceError::InvalidArgument);
+            }
+            Ok(tensor
+                .data_ref()
+                .chunks_exact(T::TP.byte_size())
+                .map(T::from_ne_bytes))
+        }
+
+        fn convert<T: InferenceTensorElement + Into<i64>>(
+            tensor: &InferenceTensor,
+            quantization: Option<&InferenceTensorQuantization>,
+            out: &mut Vec<f32>,
+        ) -> Result<(), InferenceError> {
+            let values = values::<T>(tensor)?.map(|v| v.into());
+            match quantization {
+                Some(q) => out.extend(values.map(|v| q.dequantize(v))),
+                None => out.extend(values.map(|v| v as f32)),
+            }
+            Ok(())
+        }
+
+        out.clear();
+        match self.tp {
+            InferenceTensorType::F32 => out.extend(values::<f32>(self)?),
+            InferenceTensorType::U8 => convert::<u8>(self, quantization, out)?,
+            InferenceTensorType::I8 => convert::<i8>(self, quantization, out)?,
+            InferenceTensorType::I32 => convert::<i32>(self, quantization, out)?,
+            InferenceTensorType::F16 => out.extend(values::<f16>(self)?.map(|v| v.to_f32())),
+        }
+        Ok(())
+    }
+
+    /// convert a F32 tensor to a F16 tensor, the shape and layout are not changed
+    pub fn to_f16_tensor(&self) -> Result<InferenceTensor<'static>, InferenceError> {
+        let data = self
+            .to_vec::<f32>()?
+            .into_iter()
+            .map(f16::from_f32)
+            .collect::<Vec<f16>>();
+        Ok(InferenceTensor::from_elements(
+            self.data_layout.clone(),
+            self.shape_ref().to_vec(),
+            &data,
+        ))
+    }
+
+    /// convert a F16 tensor to a F32 tensor, the shape and layout are not changed
+    pub fn to_f32_tensor(&self) -> Result<InferenceTensor<'static>, InferenceError> {
+        let data = self
+            .to_vec::<f16>()?
+            .into_iter()
+            .map(|v| v.to_f32())
+            .collect::<Vec<f32>>();
+        Ok(InferenceTensor::from_elements(
+            self.data_layout.clone(),
+            self.shape_ref().to_vec(),
+            &data,
+        ))
+    }
+
+    /// stack tensors along the first dimension (batch), such as N tensors ```[1, H, W, C]``` to
+    /// ```[N, H, W, C]```. the tensors must have the same type, layout and other dimensions.
+    pub fn stack(tensors: &[InferenceTensor]) -> Result<InferenceTensor<'static>, InferenceError> {
+        let first = tensors.first().ok_or(InferenceError::InvalidArgument)?;
+        let first_shape = first.shape_ref();
+        if first_shape.is_empty() {
+            return Err(InferenceError::InvalidArgument);
+        }
+        let mut batch = 0;
+        let mut data = Vec::with_capacity(first.data_ref().len() * tensors.len());
+        for t in tensors {
+            let shape = t.shape_ref();
+            if t.tp != first.tp
+                || t.data_layout != first.data_layout
+                || shape.len() != first_shape.len()
+                || shape[1..] != first_shape[1..]
+            {
+                return Err(InferenceError::InvalidArgument);
+            }
+            batch += shape[0];
+            data.extend_from_slice(t.data_ref());
+        }
+        let mut shape = first_shape.to_vec();
+        shape[0] = batch;
+        Ok(InferenceTensor::new(
+            first.tp.clone(),
+            first.data_layout.clone(),
+            shape,
+            data,
+        ))
+    }
+
+    /// convert to an owned tensor, the data of ref tensor is copied
     pub fn to_owned(self) -> InferenceTensor<'static> {
-        todo!()
+        let data = match self.data {
+            TensorData::Owned { shape, data } => TensorData::Owned { shape, data },
+            TensorData::Ref {
+                shape_ref,
+                data_ref,
+            } => TensorData::Owned {
+                shape: shape_ref.to_vec(),
+                data: data_ref.to_vec(),
+            },
+        };
+        InferenceTensor {
+            tp: self.tp,
+            data_layout: self.data_layout,
+            data,
+        }
+    }
+
+    /// change the shape of tensor without changing the data, the elements count must not be changed.
+    /// the data of ref tensor is copied.
+    pub fn reshape(self, shape: Vec<u32>) -> Result<InferenceTensor<'static>, InferenceError> {
+        // the size may overflow in 32-bit targets (such as wasm32)
+        let byte_len = shape
+            .iter()
+            .try_fold(self.tp.byte_size(), |len, d| len.checked_mul(*d as usize))
+            .ok_or(InferenceError::InvalidArgument)?;
+        if byte_len != self.data_ref().len() {
+            return Err(InferenceError::InvalidArgument);
+        }
+        let data = match self.data {
+            TensorData::Owned { data, .. } => data,
+            TensorData::Ref { data_ref, .. } => data_ref.to_vec(),
+        };
+        Ok(InferenceTensor::new(self.tp, self.data_layout, shape, data))
+    }
+
+    /// rearrange the data to the ```data_layout```, the shape must have 4 dimensions
+    /// which are ordered by the current data layout.
+    /// e.g. a NHWC tensor with shape ```[1, 128, 128, 3]``` to NCHW is ```[1, 3, 128, 128]```
+    pub fn transpose_to(
+        &self,
+        data_layout: InferenceTensorDataLayout,
+    ) -> Result<InferenceTensor<'static>, InferenceError> {
+        let (shape, data) = self.tensor_data_ref();
+        let elem_size = self.tp.byte_size();
+        let count: usize = shape.iter().map(|d| *d as usize).product();
+        if shape.len() != 4 || count * elem_size != data.len() {
+            return Err(InferenceError::InvalidArgument);
+        }
+
+        // perm[i] is the source axis of the target axis i
+        let (src_axes, dst_axes) = match (self.data_layout.axes(), data_layout.axes()) {
+            (Some(src_axes), Some(dst_axes)) => (src_axes, dst_axes),
+            _ => return Err(InferenceError::InvalidArgument),
+        };
+        let perm = dst_axes.map(|a| src_axes.iter().position(|s| *s == a).unwrap());
+        let dst_shape = perm.map(|p| shape[p]);
+
+        // the source strides (in elements), ordered by the target axes
+        let mut src_strides = [0usize; 4];
+        let mut stride = 1;
+        for i in (0..4).rev() {
+            src_strides[i] = stride;
+            stride *= shape[i] as usize;
+        }
+        let strides = perm.map(|p| src_strides[p]);
+
+        let mut out = Vec::with_capacity(data.len());
+        for i0 in 0..dst_shape[0] as usize {
+            for i1 in 0..dst_shape[1] as usize {
+                for i2 in 0..dst_shape[2] as usize {
+                    let base = i0 * strides[0] + i1 * strides[1] + i2 * strides[2];
+                    for i3 in 0..dst_shape[3] as usize {
+                        let offset = (base + i3 * strides[3]) * elem_size;
+                        out.extend_from_slice(&data[offset..offset + elem_size]);
+                    }
+                }
+            }
+        }
+        Ok(InferenceTensor::new(
+            self.tp.clone(),
+            data_layout,
+            dst_shape.to_vec(),
+            out,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_f16_conversion() {
+        let values = [0.0f32, -1.5, 0.333, 1024.0, 65504.0];
+        let tensor =
+            InferenceTensor::from_elements(InferenceTensorDataLayout::NHWC, vec![1, 5], &values);
+
+        let f16_tensor = tensor.to_f16_tensor().unwrap();
+        assert_eq!(f16_tensor.tp(), InferenceTensorType::F16);
+        assert_eq!(f16_tensor.data_ref().len(), 10);
+        assert_eq!(f16_tensor.shape_ref(), &[1, 5]);
+        assert!(f16_tensor.to_f16_tensor().is_err());
+
+        let decoded = f16_tensor.to_f32_tensor().unwrap().to_vec::<f32>().unwrap();
+        assert_eq!(decoded, f16_tensor.dequantize(None).unwrap());
+        for (v, d) in values.iter().zip(decoded.iter()) {
+            assert!((v - d).abs() <= v.abs() * 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_to_owned_and_reshape() {
+        let shape = [2, 3];
+        let data = [1u8, 2, 3, 4, 5, 6];
+        let tensor = InferenceTensor::new_ref(
+            InferenceTensorType::U8,
+            InferenceTensorDataLayout::NHWC,
+            &shape,
+            &data,
+        );
+        let owned = tensor.to_owned();
+        assert_eq!(owned.tensor_data_ref(), (&shape[..], &data[..]));
+
+        let reshaped = owned.reshape(vec![3, 2]).unwrap();
+        assert_eq!(reshaped.shape_ref(), &[3, 2]);
+        assert_eq!(reshaped.data_ref(), &data);
+        assert!(reshaped.reshape(vec![4, 2]).is_err());
+
+        // the size overflow is an error
+        let tensor = InferenceTensor::new_ref(
+            InferenceTensorType::F32,
+            InferenceTensorDataLayout::NHWC,
+            &shape,
+            &[0; 24],
+        );
+        assert!(matches!(
+            tensor.reshape(vec![u32::MAX, u32::MAX, u32::MAX, 2, 3]),
+            Err(InferenceError::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn test_stack() {
+        let a = InferenceTensor::from_elements(
+            InferenceTensorDataLayout::NHWC,
+            vec![1, 2, 1, 1],
+            &[1u8, 2],
+        );
+        let b = InferenceTensor::from_elements(
+            InferenceTensorDataLayout::NHWC,
+            vec![2, 2, 1, 1],
+            &[3u8, 4, 5, 6],
+        );
+        let stacked = InferenceTensor::stack(&[a, b]).unwrap();
+        assert_eq!(stacked.shape_ref(), &[3, 2, 1, 1]);
+        assert_eq!(stacked.data_ref(), &[1, 2, 3, 4, 5, 6]);
+
+        let c = InferenceTensor::from_elements(
+            InferenceTensorDataLayout::NHWC,
+            vec![1, 1, 2, 1],
+            &[1u8, 2],
+        );
+        assert!(InferenceTensor::stack(&[stacked, c]).is_err());
+        assert!(InferenceTensor::stack(&[]).is_err());
+    }
+
+    #[test]
+    fn test_transpose() {
+        // NHWC [1, 2, 2, 3], the value is (h * 2 + w) * 10 + c
+        let values = (0..4)
+            .flat_map(|p| (0..3).map(move |c: i32| p * 10 + c))
+            .collect::<Vec<_>>();
+        let nhwc = InferenceTensor::from_elements(
+            InferenceTensorDataLayout::NHWC,
+            vec![1, 2, 2, 3],
+            &values,
+        );
+
+        let nchw = nhwc.transpose_to(InferenceTensorDataLayout::NCHW).unwrap();
+        assert_eq!(nchw.shape_ref(), &[1, 3, 2, 2]);
+        assert_eq!(nchw.data_layout(), &InferenceTensorDataLayout::NCHW);
+        assert_eq!(
+            nchw.to_vec::<i32>().unwrap(),
+            vec![0, 10, 20, 30, 1, 11, 21, 31, 2, 12, 22, 32]
+        );
+
+        let chwn = nchw.transpose_to(InferenceTensorDataLayout::CHWN).unwrap();
+        assert_eq!(chwn.shape_ref(), &[3, 2, 2, 1]);
+        assert_eq!(chwn.data_ref(), nchw.data_ref());
+
+        let back = chwn.transpose_to(InferenceTensorDataLayout::NHWC).unwrap();
+        assert_eq!(back.shape_ref(), nhwc.shape_ref());
+        assert_eq!(back.data_ref(), nhwc.data_ref());
+        assert!(nhwc
+            .transpose_to(InferenceTensorDataLayout::Unknown)
+            .is_err());
+
+        let flat = nhwc.reshape(vec![12]).unwrap();
+        assert!(flat.transpose_to(InferenceTensorDataLayout::NCHW).is_err());
     }
 }
@@ -1,7 +1,19 @@
+mod inference_backend;
 mod inference_error;
+mod inference_executor_pool;
 mod inference_graph;
 mod inference_tensor;
+mod replay_backend;
+pub(crate) mod tflite_model;
+#[cfg(target_arch = "wasm32")]
+mod wasi_nn_backend;
 
+pub use half::f16;
+pub use inference_backend::*;
 pub use inference_error::*;
+pub use inference_executor_pool::*;
 pub use inference_graph::*;
 pub use inference_tensor::*;
+pub use replay_backend::*;
+#[cfg(target_arch = "wasm32")]
+pub use wasi_nn_backend::*;
@@ -0,0 +1,782 @@
+use super::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// FNV-1a 64 bits hash, it is stable between platforms and runs.
+#[derive(Debug, Clone)]
+struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    #[inline(always)]
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    #[inline(always)]
+    fn write_u32(&mut self, v: u32) {
+        self.write(&v.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_u64(&mut self, v: u64) {
+        self.write(&v.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Replay Key
+/// a recorded output set is keyed by the hash of model content and the hash of all inputs.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ReplayKey {
+    pub model_hash: u64,
+    pub input_hash: u64,
+}
+
+impl ReplayKey {
+    /// generate the key from model builders and the inputs set before ```compute```
+    pub fn new(builders: &[&[u8]], inputs: &[(u32, &InferenceTensor)]) -> Self {
+        let mut context_inputs = ContextInputs::default();
+        for (index, tensor) in inputs {
+            context_inputs.set(*index, tensor);
+        }
+        Self {
+            model_hash: Self::hash_model(builders),
+            input_hash: context_inputs.hash(),
+        }
+    }
+
+    /// the hash of model content
+    pub fn hash_model(builders: &[&[u8]]) -> u64 {
+        let mut hasher = StableHasher::new();
+        for part in builders {
+            hasher.write_u64(part.len() as u64);
+            hasher.write(part);
+        }
+        hasher.finish()
+    }
+
+    /// the hash of one input tensor
+    fn hash_tensor(tensor: &InferenceTensor) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_u32(match tensor.tp() {
+            InferenceTensorType::F16 => 0,
+            InferenceTensorType::F32 => 1,
+            InferenceTensorType::U8 => 2,
+            InferenceTensorType::I32 => 3,
+            InferenceTensorType::I8 => 4,
+        });
+        let (shape, data) = tensor.tensor_data_ref();
+        hasher.write_u32(shape.len() as u32);
+        for d in shape {
+            hasher.write_u32(*d);
+        }
+        hasher.write(data);
+        hasher.finish()
+    }
+
+    /// the file name used by [`RecordingBackend`] and [`ReplayBackend::load_dir`]
+    /// format: ```{compute sequence}-{model hash}-{input hash}-{output index}.bin```
+    fn file_name(&self, seq: u64, index: u32) -> String {
+        format!(
+            "{:08}-{:016x}-{:016x}-{}.bin",
+            seq, self.model_hash, self.input_hash, index
+        )
+    }
+
+    fn from_file_name(name: &str) -> Option<(u64, Self, u32)> {
+        let mut parts = name.strip_suffix(".bin")?.split('-');
+        let seq = parts.next()?.parse().ok()?;
+        let model_hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let input_hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let index = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some((
+            seq,
+            Self {
+                model_hash,
+                input_hash,
+            },
+            index,
+        ))
+    }
+}
+
+/// the inputs set to a execution context, only the hash is stored.
+#[derive(Debug, Clone, Default)]
+struct ContextInputs(BTreeMap<u32, u64>);
+
+impl ContextInputs {
+    #[inline]
+    fn set(&mut self, index: u32, tensor: &InferenceTensor) {
+        self.0.insert(index, ReplayKey::hash_tensor(tensor));
+    }
+
+    /// the hash is independent of the order of ```set_input```
+    #[inline]
+    fn hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        for (index, tensor_hash) in &self.0 {
+            hasher.write_u32(*index);
+            hasher.write_u64(*tensor_hash);
+        }
+        hasher.finish()
+    }
+}
+
+/// outputs of one ```compute```, with output index
+type ReplayOutputs = BTreeMap<u32, Vec<u8>>;
+
+#[derive(Debug)]
+struct ReplayContext {
+    model_hash: u64,
+    inputs: ContextInputs,
+    outputs: Option<ReplayOutputs>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    keyed: HashMap<ReplayKey, ReplayOutputs>,
+    ordered: VecDeque<ReplayOutputs>,
+    graphs: HashMap<InferenceGraphHandle, u64>,
+    contexts: HashMap<InferenceExecutionContextHandle, ReplayContext>,
+    next_handle: u32,
+}
+
+impl ReplayState {
+    #[inline]
+    fn new_handle(&mut self) -> u32 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+}
+
+/// Replay Backend
+/// a deterministic backend which serves pre-recorded output tensors instead of doing inference.
+/// it can run in any target, so the solutions can be tested without wasi-nn.
+///
+/// when ```compute```, the outputs are looked up by [`ReplayKey`] (model hash + input hash) firstly,
+/// if there is no such key, the next outputs in call order will be used.
+/// the outputs found by key are not removed from the call order queue.
+///
+/// ### Examples
+///
+/// #### replay the outputs recorded by [`RecordingBackend`]
+/// ```no_run
+/// use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, ReplayBackend};
+/// let path = "./module.tflite";
+/// let backend = ReplayBackend::load_dir("./recorded")?;
+/// let graph = InferenceGraphBuilder::default().backend(backend).build_from_file(path)?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ReplayBackend {
+    state: Mutex<ReplayState>,
+}
+
+impl ReplayBackend {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add the outputs (index is the output index) for the key
+    pub fn with_outputs(self, key: ReplayKey, outputs: Vec<Vec<u8>>) -> Self {
+        self.state.lock().unwrap().keyed.insert(
+            key,
+            outputs
+                .into_iter()
+                .enumerate()
+                .map(|(i, o)| (i as u32, o))
+                .collect(),
+        );
+        self
+    }
+
+    /// append the outputs (index is the output index) for the next ```compute``` in call order
+    pub fn push_outputs(self, outputs: Vec<Vec<u8>>) -> Self {
+        self.state.lock().unwrap().ordered.push_back(
+            outputs
+                .into_iter()
+                .enumerate()
+                .map(|(i, o)| (i as u32, o))
+                .collect(),
+        );
+        self
+    }
+
+    /// load all outputs in the directory which is written by [`RecordingBackend`]
+    /// the outputs can be found both by key and by call order.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, InferenceError> {
+        let mut records = BTreeMap::<u64, (ReplayKey, ReplayOutputs)>::new();
+        let dir = dir.as_ref();
+        for entry in std::fs::read_dir(dir).map_err(|e| InferenceError::io_error(dir, e))? {
+            let path = entry.map_err(|e| InferenceError::io_error(dir, e))?.path();
+            let parsed = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(ReplayKey::from_file_name);
+            if let Some((seq, key, index)) = parsed {
+                let data = std::fs::read(&path).map_err(|e| InferenceError::io_error(&path, e))?;
+                records
+                    .entry(seq)
+                    .or_insert_with(|| (key, ReplayOutputs::new()))
+                    .1
+                    .insert(index, data);
+            }
+        }
+
+        let mut state = ReplayState::default();
+        for (_, (key, outputs)) in records {
+            state.keyed.insert(key, outputs.clone());
+            state.ordered.push_back(outputs);
+        }
+        Ok(Self {
+            state: Mutex::new(state),
+        })
+    }
+
+    /// the number of recorded outputs which have not been used in call order
+    pub fn remaining(&self) -> usize {
+        self.state.lock().unwrap().ordered.len()
+    }
+
+    /// the number of graphs which are not released
+    pub fn live_graphs(&self) -> usize {
+        self.state.lock().unwrap().graphs.len()
+    }
+
+    /// the number of execution contexts which are not released
+    pub fn live_execution_contexts(&self) -> usize {
+        self.state.lock().unwrap().contexts.len()
+    }
+}
+
+impl InferenceBackend for ReplayBackend {
+    fn load(
+        &self,
+        builders: &[&[u8]],
+        _encoding: InferenceGraphEncoding,
+        _device: InferenceGraphDevice,
+    ) -> Result<InferenceGraphHandle, InferenceError> {
+        let mut state = self.state.lock().unwrap();
+        let handle = state.new_handle();
+        state.graphs.insert(handle, ReplayKey::hash_model(builders));
+        Ok(handle)
+    }
+
+    /// the graph loaded by name is keyed by the hash of the name
+    fn load_by_name(&self, name: &str) -> Result<InferenceGraphHandle, InferenceError> {
+        let mut state = self.state.lock().unwrap();
+        let handle = state.new_handle();
+        state
+            .graphs
+            .insert(handle, ReplayKey::hash_model(&[name.as_bytes()]));
+        Ok(handle)
+    }
+
+    fn init_execution_context(
+        &self,
+        graph: InferenceGraphHandle,
+    ) -> Result<InferenceExecutionContextHandle, InferenceError> {
+        let mut state = self.state.lock().unwrap();
+        let model_hash = *state
+            .graphs
+            .get(&graph)
+            .ok_or(InferenceError::InvalidArgument)?;
+        let handle = state.new_handle();
+        state.contexts.insert(
+            handle,
+            ReplayContext {
+                model_hash,
+                inputs: ContextInputs::default(),
+                outputs: None,
+            },
+        );
+        Ok(handle)
+    }
+
+    fn set_input(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        tensor: &InferenceTensor,
+    ) -> Result<(), InferenceError> {
+        let mut state = self.state.lock().unwrap();
+        let context = state
+            .contexts
+            .get_mut(&ctx)
+            .ok_or(InferenceError::InvalidArgument)?;
+        context.inputs.set(index, tensor);
+        Ok(())
+    }
+
+    fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError> {
+        let mut state = self.state.lock().unwrap();
+        let key = {
+            let context = state
+                .contexts
+                .get(&ctx)
+                .ok_or(InferenceError::InvalidArgument)?;
+            ReplayKey {
+                model_hash: context.model_hash,
+                input_hash: context.inputs.hash(),
+            }
+        };
+        let outputs = match state.keyed.get(&key) {
+            Some(outputs) => outputs.clone(),
+            None => state
+                .ordered
+                .pop_front()
+                .ok_or(InferenceError::RuntimeError)?,
+        };
+        state.contexts.get_mut(&ctx).unwrap().outputs = Some(outputs);
+        Ok(())
+    }
+
+    fn get_output(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        buf: &mut [u8],
+    ) -> Result<u32, InferenceError> {
+        let state = self.state.lock().unwrap();
+        let output = state
+            .contexts
+            .get(&ctx)
+            .and_then(|c| c.outputs.as_ref())
+            .and_then(|o| o.get(&index))
+            .ok_or(InferenceError::InvalidArgument)?;
+        if output.len() > buf.len() {
+            return Err(InferenceError::OutputGetLenError {
+                index,
+                expect: buf.len() as u32,
+                got: output.len() as u32,
+            });
+        }
+        buf[..output.len()].copy_from_slice(output);
+        Ok(output.len() as u32)
+    }
+
+    fn drop_graph(&self, graph: InferenceGraphHandle) -> Result<(), InferenceError> {
+        self.state
+            .lock()
+            .unwrap()
+            .graphs
+            .remove(&graph)
+            .map(|_| ())
+            .ok_or(InferenceError::InvalidArgument)
+    }
+
+    fn drop_execution_context(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+    ) -> Result<(), InferenceError> {
+        self.state
+            .lock()
+            .unwrap()
+            .contexts
+            .remove(&ctx)
+            .map(|_| ())
+            .ok_or(InferenceError::InvalidArgument)
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecordingState {
+    graphs: HashMap<InferenceGraphHandle, u64>,
+    contexts: HashMap<InferenceExecutionContextHandle, (u64, ContextInputs, u64)>,
+    seq: u64,
+}
+
+/// Recording Backend
+/// a wrapper for another backend (such as wasi-nn backend), it dumps every output got from
+/// the inner backend to files, and these files can be loaded by [`ReplayBackend::load_dir`].
+///
+/// ### Examples
+///
+/// #### record all outputs when running in WasmEdge
+/// (ignored in doc tests, ```WasiNnBackend``` only exists in ```wasm32``` target)
+/// ```ignore
+/// use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, RecordingBackend, WasiNnBackend};
+/// let path = "./module.tflite";
+/// let backend = RecordingBackend::new(WasiNnBackend, "./recorded");
+/// let graph = InferenceGraphBuilder::default().backend(backend).build_from_file(path)?;
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
+/// ```
+#[derive(Debug)]
+pub struct RecordingBackend<B: InferenceBackend> {
+    inner: B,
+    dir: PathBuf,
+    state: Mutex<RecordingState>,
+}
+
+impl<B: InferenceBackend> RecordingBackend<B> {
+    /// the directory must exist and can be written
+    pub fn new(inner: B, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+            state: Mutex::new(RecordingState::default()),
+        }
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    #[inline(always)]
+    pub fn dir(&self) -> &Path {
+        self.dir.as_path()
+    }
+}
+
+impl<B: InferenceBackend> InferenceBackend for RecordingBackend<B> {
+    fn load(
+        &self,
+        builders: &[&[u8]],
+        encoding: InferenceGraphEncoding,
+        device: InferenceGraphDevice,
+    ) -> Result<InferenceGraphHandle, InferenceError> {
+        let handle = self.inner.load(builders, encoding, device)?;
+        self.state
+            .lock()
+            .unwrap()
+            .graphs
+            .insert(handle, ReplayKey::hash_model(builders));
+        Ok(handle)
+    }
+
+    fn load_by_name(&self, name: &str) -> Result<InferenceGraphHandle, InferenceError> {
+        let handle = self.inner.load_by_name(name)?;
+        self.state
+            .lock()
+            .unwrap()
+            .graphs
+            .insert(handle, ReplayKey::hash_model(&[name.as_bytes()]));
+        Ok(handle)
+    }
+
+    fn init_execution_context(
+        &self,
+        graph: InferenceGraphHandle,
+    ) -> Result<InferenceExecutionContextHandle, InferenceError> {
+        let handle = self.inner.init_execution_context(graph)?;
+        let mut state = self.state.lock().unwrap();
+        let model_hash = state.graphs.get(&graph).cloned().unwrap_or_default();
+        state
+            .contexts
+            .insert(handle, (model_hash, ContextInputs::default(), 0));
+        Ok(handle)
+    }
+
+    fn set_input(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        tensor: &InferenceTensor,
+    ) -> Result<(), InferenceError> {
+        self.inner.set_input(ctx, index, tensor)?;
+        if let Some((_, inputs, _)) = self.state.lock().unwrap().contexts.get_mut(&ctx) {
+            inputs.set(index, tensor);
+        }
+        Ok(())
+    }
+
+    fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError> {
+        self.inner.compute(ctx)?;
+        let mut state = self.state.lock().unwrap();
+        let seq = state.seq;
+        state.seq += 1;
+        if let Some((_, _, context_seq)) = state.contexts.get_mut(&ctx) {
+            *context_seq = seq;
+        }
+        Ok(())
+    }
+
+    fn get_output(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        buf: &mut [u8],
+    ) -> Result<u32, InferenceError> {
+        let len = self.inner.get_output(ctx, index, buf)?;
+        // the inner backend may report a length larger than the buffer
+        if len as usize > buf.len() {
+            return Err(InferenceError::OutputGetLenError {
+                index,
+                expect: buf.len() as u32,
+                got: len,
+            });
+        }
+        let file_name = match self.state.lock().unwrap().contexts.get(&ctx) {
+            Some((model_hash, inputs, seq)) => ReplayKey {
+                model_hash: *model_hash,
+                input_hash: inputs.hash(),
+            }
+            .file_name(*seq, index),
+            None => return Ok(len),
+        };
+        let path = self.dir.join(file_name);
+        std::fs::write(&path, &buf[..len as usize])
+            .map_err(|e| InferenceError::io_error(&path, e))?;
+        Ok(len)
+    }
+
+    fn drop_graph(&self, graph: InferenceGraphHandle) -> Result<(), InferenceError> {
+        self.state.lock().unwrap().graphs.remove(&graph);
+        self.inner.drop_graph(graph)
+    }
+
+    fn drop_execution_context(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+    ) -> Result<(), InferenceError> {
+        self.state.lock().unwrap().contexts.remove(&ctx);
+        self.inner.drop_execution_context(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn u8_tensor(data: Vec<u8>) -> InferenceTensor<'static> {
+        InferenceTensor::new(
+            InferenceTensorType::U8,
+            InferenceTensorDataLayout::NHWC,
+            vec![1, data.len() as u32],
+            data,
+        )
+    }
+
+    fn build_graph(backend: impl InferenceBackend + 'static) -> InferenceGraph {
+        InferenceGraphBuilder::default()
+            .backend(backend)
+            .build_from_bytes(vec![1, 2, 3])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_replay_by_key_and_order() {
+        let key = ReplayKey::new(&[&[1, 2, 3]], &[(0, &u8_tensor(vec![7]))]);
+        let backend = ReplayBackend::new()
+            .with_outputs(key, vec![vec![70]])
+            .push_outputs(vec![vec![1], vec![2, 2]])
+            .push_outputs(vec![vec![3]]);
+        let graph = build_graph(backend);
+        let mut executor = graph.new_graph_executor().unwrap();
+
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![0]))])
+            .unwrap();
+        assert_eq!(executor.get_output_u8(1, 2).unwrap(), vec![2, 2]);
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![7]))])
+            .unwrap();
+        assert_eq!(executor.get_output_u8(0, 1).unwrap(), vec![70]);
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![0]))])
+            .unwrap();
+        assert_eq!(executor.get_output_u8(0, 1).unwrap(), vec![3]);
+        assert!(executor
+            .set_inputs_and_run([(0, u8_tensor(vec![0]))])
+            .is_err());
+    }
+
+    #[test]
+    fn test_replay_small_buffer() {
+        let backend = ReplayBackend::new().push_outputs(vec![vec![1], vec![2, 2]]);
+        let graph = build_graph(backend);
+        let mut executor = graph.new_graph_executor().unwrap();
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![0]))])
+            .unwrap();
+        // expect is the buffer size of the caller, got is the size of the output
+        assert!(matches!(
+            executor.get_output_u8(1, 1),
+            Err(InferenceError::OutputGetLenError {
+                index: 1,
+                expect: 1,
+                got: 2
+            })
+        ));
+    }
+
+    /// a backend which reports one more byte than the replayed output
+    #[derive(Debug)]
+    struct OverReportBackend(ReplayBackend);
+
+    impl InferenceBackend for OverReportBackend {
+        fn load(
+            &self,
+            builders: &[&[u8]],
+            encoding: InferenceGraphEncoding,
+            device: InferenceGraphDevice,
+        ) -> Result<InferenceGraphHandle, InferenceError> {
+            self.0.load(builders, encoding, device)
+        }
+
+        fn init_execution_context(
+            &self,
+            graph: InferenceGraphHandle,
+        ) -> Result<InferenceExecutionContextHandle, InferenceError> {
+            self.0.init_execution_context(graph)
+        }
+
+        fn set_input(
+            &self,
+            ctx: InferenceExecutionContextHandle,
+            index: u32,
+            tensor: &InferenceTensor,
+        ) -> Result<(), InferenceError> {
+            self.0.set_input(ctx, index, tensor)
+        }
+
+        fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError> {
+            self.0.compute(ctx)
+        }
+
+        fn get_output(
+            &self,
+            ctx: InferenceExecutionContextHandle,
+            index: u32,
+            buf: &mut [u8],
+        ) -> Result<u32, InferenceError> {
+            Ok(self.0.get_output(ctx, index, buf)? + 1)
+        }
+    }
+
+    #[test]
+    fn test_record_over_reported_length() {
+        let dir = std::env::temp_dir().join(format!("replay-over-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let inner = OverReportBackend(ReplayBackend::new().push_outputs(vec![vec![1, 1]]));
+        let graph = build_graph(RecordingBackend::new(inner, &dir));
+        let mut executor = graph.new_graph_executor().unwrap();
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![0]))])
+            .unwrap();
+        assert!(matches!(
+            executor.get_output_u8(0, 2),
+            Err(InferenceError::OutputGetLenError {
+                index: 0,
+                expect: 2,
+                got: 3
+            })
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_release_handles() {
+        let backend = std::sync::Arc::new(ReplayBackend::new());
+        let graph = build_graph(backend.clone());
+        let executors = [
+            graph.new_graph_executor().unwrap(),
+            graph.new_graph_executor().unwrap(),
+        ];
+        assert_eq!(backend.live_graphs(), 1);
+        assert_eq!(backend.live_execution_contexts(), 2);
+
+        drop(executors);
+        assert_eq!(backend.live_execution_contexts(), 0);
+        drop(graph);
+        assert_eq!(backend.live_graphs(), 0);
+    }
+
+    #[test]
+    fn test_replay_multi_parts() {
+        let dir = std::env::temp_dir().join(format!("replay-parts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("model.xml"), [1, 2]).unwrap();
+        std::fs::write(dir.join("model.bin"), [3]).unwrap();
+
+        let input = u8_tensor(vec![0]);
+        let key = ReplayKey::new(&[&[1, 2], &[3]], &[(0, &input)]);
+        let graph = InferenceGraphBuilder::default()
+            .encoding(InferenceGraphEncoding::Openvino)
+            .backend(ReplayBackend::new().with_outputs(key, vec![vec![9]]))
+            .build_from_files([dir.join("model.xml"), dir.join("model.bin")])
+            .unwrap();
+        let mut executor = graph.new_graph_executor().unwrap();
+        executor.set_inputs_and_run([(0, input)]).unwrap();
+        assert_eq!(executor.get_output_u8(0, 1).unwrap(), vec![9]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = std::env::temp_dir().join(format!("replay-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let recorded = ReplayBackend::new()
+            .push_outputs(vec![vec![1, 1], vec![2]])
+            .push_outputs(vec![vec![3, 3], vec![4]]);
+        let graph = build_graph(RecordingBackend::new(recorded, &dir));
+        let mut executor = graph.new_graph_executor().unwrap();
+        for i in 0..2 {
+            executor
+                .set_inputs_and_run([(0, u8_tensor(vec![i]))])
+                .unwrap();
+            executor.get_output_u8(0, 2).unwrap();
+            executor.get_output_u8(1, 1).unwrap();
+        }
+
+        let graph = build_graph(ReplayBackend::load_dir(&dir).unwrap());
+        let mut executor = graph.new_graph_executor().unwrap();
+        // replay by key: the input order is different from recording
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![1]))])
+            .unwrap();
+        assert_eq!(executor.get_output_u8(0, 2).unwrap(), vec![3, 3]);
+        assert_eq!(executor.get_output_u8(1, 1).unwrap(), vec![4]);
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![0]))])
+            .unwrap();
+        assert_eq!(executor.get_output_u8(0, 2).unwrap(), vec![1, 1]);
+        assert_eq!(executor.get_output_u8(1, 1).unwrap(), vec![2]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_load_by_name() {
+        let key = ReplayKey::new(&[b"face"], &[(0, &u8_tensor(vec![7]))]);
+        let backend = std::sync::Arc::new(ReplayBackend::new().with_outputs(key, vec![vec![70]]));
+        let graph = InferenceGraphBuilder::default()
+            .backend(backend.clone())
+            .build_from_name("face")
+            .unwrap();
+        assert!(graph.inputs().is_empty());
+
+        let mut executor = graph.new_graph_executor().unwrap();
+        executor
+            .set_inputs_and_run([(0, u8_tensor(vec![7]))])
+            .unwrap();
+        assert_eq!(executor.get_output_u8(0, 1).unwrap(), vec![70]);
+        drop((executor, graph));
+        assert_eq!(backend.live_graphs(), 0);
+    }
+}
@@ -0,0 +1,541 @@
+// read the inputs and outputs information from a tflite model (flatbuffer format)
+//
+// schema ref: https://github.com/tensorflow/tensorflow/blob/master/tensorflow/lite/schema/schema.fbs
+// flatbuffer format ref: https://flatbuffers.dev/flatbuffers_internals.html
+
+use super::{
+    InferenceError, InferenceTensorInfo, InferenceTensorQuantization, InferenceTensorType,
+};
+
+type FbResult<T> = Result<T, InferenceError>;
+
+/// the model is not a valid tflite flatbuffer
+#[inline(always)]
+fn corrupt() -> InferenceError {
+    InferenceError::InvalidEncoding
+}
+
+/// ```pos + offset```, the offsets are from the model, so it may overflow in 32-bit targets
+#[inline(always)]
+fn offset_pos(pos: usize, offset: usize) -> FbResult<usize> {
+    pos.checked_add(offset).ok_or_else(corrupt)
+}
+
+#[inline]
+fn read_bytes<const N: usize>(buf: &[u8], pos: usize) -> FbResult<[u8; N]> {
+    buf.get(pos..offset_pos(pos, N)?)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(corrupt)
+}
+
+#[inline(always)]
+fn read_u16(buf: &[u8], pos: usize) -> FbResult<u16> {
+    Ok(u16::from_le_bytes(read_bytes(buf, pos)?))
+}
+
+#[inline(always)]
+fn read_u32(buf: &[u8], pos: usize) -> FbResult<u32> {
+    Ok(u32::from_le_bytes(read_bytes(buf, pos)?))
+}
+
+#[inline(always)]
+fn read_i32(buf: &[u8], pos: usize) -> FbResult<i32> {
+    Ok(i32::from_le_bytes(read_bytes(buf, pos)?))
+}
+
+/// follow the ```uoffset_t``` stored in ```pos```
+#[inline]
+fn indirect(buf: &[u8], pos: usize) -> FbResult<usize> {
+    offset_pos(pos, read_u32(buf, pos)? as usize)
+}
+
+#[derive(Clone, Copy)]
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Table<'a> {
+    fn root(buf: &'a [u8]) -> FbResult<Self> {
+        Ok(Self {
+            buf,
+            pos: indirect(buf, 0)?,
+        })
+    }
+
+    /// the position of the field, None if the field is absent
+    fn field_pos(&self, id: usize) -> FbResult<Option<usize>> {
+        let vtable = (self.pos as i64) - (read_i32(self.buf, self.pos)? as i64);
+        if vtable < 0 {
+            return Err(corrupt());
+        }
+        let vtable = vtable as usize;
+        let entry = 4 + 2 * id;
+        if entry + 2 > read_u16(self.buf, vtable)? as usize {
+            return Ok(None);
+        }
+        Ok(match read_u16(self.buf, offset_pos(vtable, entry)?)? {
+            0 => None,
+            offset => Some(offset_pos(self.pos, offset as usize)?),
+        })
+    }
+
+    fn u8_field(&self, id: usize, default: u8) -> FbResult<u8> {
+        match self.field_pos(id)? {
+            Some(pos) => self.buf.get(pos).cloned().ok_or_else(corrupt),
+            None => Ok(default),
+        }
+    }
+
+    fn table(&self, id: usize) -> FbResult<Option<Table<'a>>> {
+        Ok(match self.field_pos(id)? {
+            Some(pos) => Some(Table {
+                buf: self.buf,
+                pos: indirect(self.buf, pos)?,
+            }),
+            None => None,
+        })
+    }
+
+    /// absent vector is same as empty vector.
+    /// the elements (```elem_size``` bytes each) must be in the buffer, so the length
+    /// from the model is safe to preallocate.
+    fn vector(&self, id: usize, elem_size: usize) -> FbResult<Vector<'a>> {
+        Ok(match self.field_pos(id)? {
+            Some(pos) => {
+                let pos = indirect(self.buf, pos)?;
+                let len = read_u32(self.buf, pos)? as usize;
+                let start = offset_pos(pos, 4)?;
+                let end = offset_pos(start, len.checked_mul(elem_size).ok_or_else(corrupt)?)?;
+                if end > self.buf.len() {
+                    return Err(corrupt());
+                }
+                Vector {
+                    buf: self.buf,
+                    pos: start,
+                    len,
+                }
+            }
+            None => Vector {
+                buf: self.buf,
+                pos: 0,
+                len: 0,
+            },
+        })
+    }
+
+    fn string(&self, id: usize) -> FbResult<String> {
+        let v = self.vector(id, 1)?;
+        let bytes = self
+            .buf
+            .get(v.pos..offset_pos(v.pos, v.len)?)
+            .ok_or_else(corrupt)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Vector<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    len: usize,
+}
+
+impl<'a> Vector<'a> {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// the position of the element ```i``` with ```size``` bytes
+    #[inline]
+    fn element_pos(&self, i: usize, size: usize) -> FbResult<usize> {
+        offset_pos(self.pos, i.checked_mul(size).ok_or_else(corrupt)?)
+    }
+
+    #[inline]
+    fn table(&self, i: usize) -> FbResult<Table<'a>> {
+        Ok(Table {
+            buf: self.buf,
+            pos: indirect(self.buf, self.element_pos(i, 4)?)?,
+        })
+    }
+
+    #[inline]
+    fn i32(&self, i: usize) -> FbResult<i32> {
+        read_i32(self.buf, self.element_pos(i, 4)?)
+    }
+
+    #[inline]
+    fn f32(&self, i: usize) -> FbResult<f32> {
+        Ok(f32::from_bits(read_u32(self.buf, self.element_pos(i, 4)?)?))
+    }
+
+    #[inline]
+    fn i64(&self, i: usize) -> FbResult<i64> {
+        Ok(i64::from_le_bytes(read_bytes(
+            self.buf,
+            self.element_pos(i, 8)?,
+        )?))
+    }
+}
+
+// field ids in tflite schema
+const MODEL_SUBGRAPHS: usize = 2;
+const SUBGRAPH_TENSORS: usize = 0;
+const SUBGRAPH_INPUTS: usize = 1;
+const SUBGRAPH_OUTPUTS: usize = 2;
+const TENSOR_SHAPE: usize = 0;
+const TENSOR_TYPE: usize = 1;
+const TENSOR_NAME: usize = 3;
+const TENSOR_QUANTIZATION: usize = 4;
+const TENSOR_SHAPE_SIGNATURE: usize = 7;
+const QUANTIZATION_SCALE: usize = 2;
+const QUANTIZATION_ZERO_POINT: usize = 3;
+
+/// convert the tflite ```TensorType``` enum value, None if the type is not supported
+fn tensor_type_from_tflite(tp: u8) -> Option<InferenceTensorType> {
+    match tp {
+        0 => Some(InferenceTensorType::F32),
+        1 => Some(InferenceTensorType::F16),
+        2 => Some(InferenceTensorType::I32),
+        3 => Some(InferenceTensorType::U8),
+        9 => Some(InferenceTensorType::I8),
+        _ => None,
+    }
+}
+
+/// return None if the tensor type is not supported
+fn read_tensor_info(tensor: Table) -> FbResult<Option<InferenceTensorInfo>> {
+    let tp = match tensor_type_from_tflite(tensor.u8_field(TENSOR_TYPE, 0)?) {
+        Some(tp) => tp,
+        None => return Ok(None),
+    };
+
+    let shape_vec = tensor.vector(TENSOR_SHAPE, 4)?;
+    let mut shape = Vec::with_capacity(shape_vec.len());
+    for i in 0..shape_vec.len() {
+        shape.push(u32::try_from(shape_vec.i32(i)?).map_err(|_e| corrupt())?);
+    }
+
+    // the dynamic dimensions are -1 in the shape signature, only the batch can be dynamic now
+    let signature = tensor.vector(TENSOR_SHAPE_SIGNATURE, 4)?;
+    let dynamic_batch = signature.len() > 0 && signature.i32(0)? == -1;
+
+    // only per-tensor quantization is used for inputs and outputs, so just use the first value
+    let mut quantization = None;
+    if let Some(q) = tensor.table(TENSOR_QUANTIZATION)? {
+        let scale = q.vector(QUANTIZATION_SCALE, 4)?;
+        let zero_point = q.vector(QUANTIZATION_ZERO_POINT, 8)?;
+        if scale.len() > 0 {
+            quantization = Some(InferenceTensorQuantization {
+                scale: scale.f32(0)?,
+                zero_point: if zero_point.len() > 0 {
+                    zero_point.i64(0)?
+                } else {
+                    0
+                },
+            });
+        }
+    }
+
+    Ok(Some(InferenceTensorInfo {
+        name: tensor.string(TENSOR_NAME)?,
+        shape,
+        tp,
+        quantization,
+        dynamic_batch,
+    }))
+}
+
+/// the inputs and outputs information
+pub(crate) type TfliteIo = (Vec<InferenceTensorInfo>, Vec<InferenceTensorInfo>);
+
+/// read the inputs and outputs of the main subgraph (the first subgraph).
+/// return error if the model is invalid, return None if some inputs or outputs types are not supported.
+pub(crate) fn read_tflite_io(model: &[u8]) -> Result<Option<TfliteIo>, InferenceError> {
+    let subgraphs = Table::root(model)?.vector(MODEL_SUBGRAPHS, 4)?;
+    if subgraphs.len() == 0 {
+        return Err(corrupt());
+    }
+    let subgraph = subgraphs.table(0)?;
+    let tensors = subgraph.vector(SUBGRAPH_TENSORS, 4)?;
+
+    let read_io = |id: usize| -> FbResult<Option<Vec<InferenceTensorInfo>>> {
+        let indexes = subgraph.vector(id, 4)?;
+        let mut res = Vec::with_capacity(indexes.len());
+        for i in 0..indexes.len() {
+            let index = usize::try_from(indexes.i32(i)?).map_err(|_e| corrupt())?;
+            if index >= tensors.len() {
+                return Err(corrupt());
+            }
+            match read_tensor_info(tensors.table(index)?)? {
+                Some(info) => res.push(info),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(res))
+    };
+    Ok(
+        match (read_io(SUBGRAPH_INPUTS)?, read_io(SUBGRAPH_OUTPUTS)?) {
+            (Some(inputs), Some(outputs)) => Some((inputs, outputs)),
+            _ => None,
+        },
+    )
+}
+
+/// a tiny flatbuffer writer to generate tflite models (only inputs and outputs) for tests
+#[cfg(test)]
+pub(crate) mod test_model {
+    use super::*;
+
+    enum Node {
+        /// fields with id
+        Table(Vec<(usize, Field)>),
+        VecI32(Vec<i32>),
+        VecF32(Vec<f32>),
+        VecI64(Vec<i64>),
+        VecTable(Vec<Node>),
+        Str(String),
+    }
+
+    enum Field {
+        U8(u8),
+        Offset(Node),
+    }
+
+    #[inline]
+    fn patch_offset(buf: &mut [u8], pos: usize, target: usize) {
+        buf[pos..pos + 4].copy_from_slice(&((target - pos) as u32).to_le_bytes());
+    }
+
+    /// write the node at the end of buffer (children are written after the parent), return the position
+    fn write(buf: &mut Vec<u8>, node: &Node) -> usize {
+        while buf.len() & 3 != 0 {
+            buf.push(0);
+        }
+        match node {
+            Node::Table(fields) => {
+                let max_id = fields.iter().map(|(id, _)| *id).max().unwrap_or(0);
+                let vtable_pos = buf.len();
+                let vtable_len = 4 + 2 * (max_id + 1);
+                buf.extend_from_slice(&(vtable_len as u16).to_le_bytes());
+                buf.extend_from_slice(&((4 + 4 * fields.len()) as u16).to_le_bytes());
+                let mut entries = vec![0u16; max_id + 1];
+                for (k, (id, _)) in fields.iter().enumerate() {
+                    entries[*id] = (4 + 4 * k) as u16;
+                }
+                entries
+                    .iter()
+                    .for_each(|e| buf.extend_from_slice(&e.to_le_bytes()));
+                while buf.len() & 3 != 0 {
+                    buf.push(0);
+                }
+
+                let table_pos = buf.len();
+                buf.extend_from_slice(&((table_pos - vtable_pos) as i32).to_le_bytes());
+                let mut children = Vec::new();
+                for (_, field) in fields {
+                    match field {
+                        Field::U8(v) => buf.extend_from_slice(&[*v, 0, 0, 0]),
+                        Field::Offset(child) => {
+                            children.push((buf.len(), child));
+                            buf.extend_from_slice(&[0; 4]);
+                        }
+                    }
+                }
+                for (pos, child) in children {
+                    let child_pos = write(buf, child);
+                    patch_offset(buf, pos, child_pos);
+                }
+                table_pos
+            }
+            Node::VecTable(tables) => {
+                let pos = buf.len();
+                buf.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+                buf.resize(buf.len() + 4 * tables.len(), 0);
+                for (i, t) in tables.iter().enumerate() {
+                    let child_pos = write(buf, t);
+                    patch_offset(buf, pos + 4 + 4 * i, child_pos);
+                }
+                pos
+            }
+            Node::VecI32(v) => write_vec(buf, v.len(), v.iter().flat_map(|x| x.to_le_bytes())),
+            Node::VecF32(v) => write_vec(buf, v.len(), v.iter().flat_map(|x| x.to_le_bytes())),
+            Node::VecI64(v) => write_vec(buf, v.len(), v.iter().flat_map(|x| x.to_le_bytes())),
+            Node::Str(s) => write_vec(buf, s.len(), s.bytes().chain([0])),
+        }
+    }
+
+    fn write_vec(buf: &mut Vec<u8>, len: usize, bytes: impl Iterator<Item = u8>) -> usize {
+        let pos = buf.len();
+        buf.extend_from_slice(&(len as u32).to_le_bytes());
+        buf.extend(bytes);
+        pos
+    }
+
+    fn tensor_node(info: &InferenceTensorInfo) -> Node {
+        let tp = match info.tp {
+            InferenceTensorType::F32 => 0,
+            InferenceTensorType::F16 => 1,
+            InferenceTensorType::I32 => 2,
+            InferenceTensorType::U8 => 3,
+            InferenceTensorType::I8 => 9,
+        };
+        let mut fields = vec![
+            (
+                TENSOR_SHAPE,
+                Field::Offset(Node::VecI32(info.shape.iter().map(|d| *d as i32).collect())),
+            ),
+            (TENSOR_TYPE, Field::U8(tp)),
+            (TENSOR_NAME, Field::Offset(Node::Str(info.name.clone()))),
+        ];
+        if info.dynamic_batch {
+            let mut signature = info.shape.iter().map(|d| *d as i32).collect::<Vec<_>>();
+            signature[0] = -1;
+            fields.push((
+                TENSOR_SHAPE_SIGNATURE,
+                Field::Offset(Node::VecI32(signature)),
+            ));
+        }
+        if let Some(q) = &info.quantization {
+            fields.push((
+                TENSOR_QUANTIZATION,
+                Field::Offset(Node::Table(vec![
+                    (
+                        QUANTIZATION_SCALE,
+                        Field::Offset(Node::VecF32(vec![q.scale])),
+                    ),
+                    (
+                        QUANTIZATION_ZERO_POINT,
+                        Field::Offset(Node::VecI64(vec![q.zero_point])),
+                    ),
+                ])),
+            ));
+        }
+        Node::Table(fields)
+    }
+
+    /// generate a tflite model which only has the inputs and outputs tensors
+    pub(crate) fn build_tflite_model(
+        inputs: &[InferenceTensorInfo],
+        outputs: &[InferenceTensorInfo],
+    ) -> Vec<u8> {
+        let tensors = inputs.iter().chain(outputs).map(tensor_node).collect();
+        let subgraph = Node::Table(vec![
+            (SUBGRAPH_TENSORS, Field::Offset(Node::VecTable(tensors))),
+            (
+                SUBGRAPH_INPUTS,
+                Field::Offset(Node::VecI32((0..inputs.len() as i32).collect())),
+            ),
+            (
+                SUBGRAPH_OUTPUTS,
+                Field::Offset(Node::VecI32(
+                    (inputs.len() as i32..(inputs.len() + outputs.len()) as i32).collect(),
+                )),
+            ),
+        ]);
+        let model = Node::Table(vec![(
+            MODEL_SUBGRAPHS,
+            Field::Offset(Node::VecTable(vec![subgraph])),
+        )]);
+
+        // root offset + file identifier
+        let mut buf = vec![0, 0, 0, 0, b'T', b'F', b'L', b'3'];
+        let root = write(&mut buf, &model);
+        patch_offset(&mut buf, 0, root);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_model::build_tflite_model;
+    use super::*;
+
+    #[test]
+    fn test_read_face_detection_model() {
+        let model = std::fs::read("./assets/face_detection_short_range.tflite").unwrap();
+        let (inputs, outputs) = read_tflite_io(&model).unwrap().unwrap();
+
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].shape, vec![1, 128, 128, 3]);
+        assert_eq!(inputs[0].tp, InferenceTensorType::F32);
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].shape, vec![1, 896, 16]);
+        assert_eq!(outputs[0].tp, InferenceTensorType::F32);
+        assert_eq!(outputs[1].shape, vec![1, 896, 1]);
+        assert_eq!(outputs[1].elements_count(), 896);
+        assert!(!inputs[0].dynamic_batch);
+    }
+
+    #[test]
+    fn test_read_quantized_model() {
+        let input = InferenceTensorInfo {
+            name: "input".to_string(),
+            shape: vec![1, 4, 4, 3],
+            tp: InferenceTensorType::U8,
+            quantization: Some(InferenceTensorQuantization {
+                scale: 1.0 / 128.0,
+                zero_point: 128,
+            }),
+            dynamic_batch: false,
+        };
+        let output = InferenceTensorInfo {
+            name: "output".to_string(),
+            shape: vec![1, 10],
+            tp: InferenceTensorType::F32,
+            quantization: None,
+            dynamic_batch: true,
+        };
+        let (inputs, outputs) = (vec![input], vec![output]);
+        let model = build_tflite_model(&inputs, &outputs);
+        assert_eq!(read_tflite_io(&model).unwrap(), Some((inputs, outputs)));
+    }
+
+    #[test]
+    fn test_read_invalid_model() {
+        assert!(read_tflite_io(&[]).is_err());
+        assert!(read_tflite_io(&[0xff; 16]).is_err());
+
+        // the offsets and lengths near usize::MAX are errors instead of overflow panics
+        assert!(read_bytes::<4>(&[0; 8], usize::MAX - 1).is_err());
+        assert!(offset_pos(usize::MAX, 1).is_err());
+        let vector = Vector {
+            buf: &[0; 8],
+            pos: 4,
+            len: usize::MAX,
+        };
+        assert!(vector.i32(usize::MAX / 2).is_err());
+        assert!(vector.i64(usize::MAX / 4).is_err());
+        assert!(vector.table(usize::MAX / 2).is_err());
+    }
+
+    #[test]
+    fn test_read_oversized_vector() {
+        let info = InferenceTensorInfo {
+            name: "input".to_string(),
+            shape: vec![1, 4, 4, 3],
+            tp: InferenceTensorType::F32,
+            quantization: None,
+            dynamic_batch: false,
+        };
+        let mut model =
+            build_tflite_model(std::slice::from_ref(&info), std::slice::from_ref(&info));
+
+        // patch the length of the first shape vector, it is an error instead of a huge allocation
+        let shape: Vec<u8> = [4i32, 1, 4, 4, 3]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let pos = model
+            .windows(shape.len())
+            .position(|w| w == shape.as_slice())
+            .unwrap();
+        model[pos..pos + 4].copy_from_slice(&0x7fff_ffffu32.to_le_bytes());
+        assert!(matches!(
+            read_tflite_io(&model),
+            Err(InferenceError::InvalidEncoding)
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+use super::*;
+use wasi_nn;
+
+impl From<wasi_nn::NnErrno> for InferenceError {
+    #[inline(always)]
+    fn from(value: wasi_nn::NnErrno) -> Self {
+        Self::from_errno(value.raw())
+    }
+}
+
+impl InferenceError {
+    /// the errno is kept, and the known errno has the corresponding error as the source
+    fn from_errno(errno: u16) -> Self {
+        const INVALID_ARGUMENT: u16 = wasi_nn::NN_ERRNO_INVALID_ARGUMENT.raw();
+        const INVALID_ENCODING: u16 = wasi_nn::NN_ERRNO_INVALID_ENCODING.raw();
+        const MISSING_MEMORY: u16 = wasi_nn::NN_ERRNO_MISSING_MEMORY.raw();
+        const BUSY: u16 = wasi_nn::NN_ERRNO_BUSY.raw();
+        const RUNTIME_ERROR: u16 = wasi_nn::NN_ERRNO_RUNTIME_ERROR.raw();
+        let source = match errno {
+            INVALID_ARGUMENT => Some(Self::InvalidArgument),
+            INVALID_ENCODING => Some(Self::InvalidEncoding),
+            MISSING_MEMORY => Some(Self::MissingMemory),
+            BUSY => Some(Self::Busy),
+            RUNTIME_ERROR => Some(Self::RuntimeError),
+            _ => None,
+        };
+        Self::WasiNnError {
+            errno,
+            source: source.map(Box::new),
+        }
+    }
+}
+
+/// the graph encoding used to load a graph
+#[derive(Debug, PartialEq)]
+enum WasiNnEncoding {
+    /// the encodings of the wasi-nn crate, loaded by ```wasi_nn::load```
+    Crate(wasi_nn::GraphEncoding),
+    /// the encodings defined by WasmEdge which have no constants in the wasi-nn crate,
+    /// loaded by the raw ```load``` with the value
+    WasmEdge(u8),
+}
+
+impl From<&InferenceGraphEncoding> for WasiNnEncoding {
+    #[inline(always)]
+    fn from(value: &InferenceGraphEncoding) -> Self {
+        match value {
+            InferenceGraphEncoding::Onnx => Self::Crate(wasi_nn::GRAPH_ENCODING_ONNX),
+            InferenceGraphEncoding::Openvino => Self::Crate(wasi_nn::GRAPH_ENCODING_OPENVINO),
+            InferenceGraphEncoding::Tensorflow => Self::Crate(wasi_nn::GRAPH_ENCODING_TENSORFLOW),
+            InferenceGraphEncoding::Pytorch => Self::Crate(wasi_nn::GRAPH_ENCODING_PYTORCH),
+            InferenceGraphEncoding::TensorflowLite => Self::WasmEdge(4),
+            #[cfg(feature = "wasi-nn-extra-encodings")]
+            InferenceGraphEncoding::Autodetect => Self::WasmEdge(5),
+            #[cfg(feature = "wasi-nn-extra-encodings")]
+            InferenceGraphEncoding::Ggml => Self::WasmEdge(6),
+        }
+    }
+}
+
+impl From<InferenceGraphDevice> for wasi_nn::ExecutionTarget {
+    #[inline]
+    fn from(value: InferenceGraphDevice) -> Self {
+        match value {
+            InferenceGraphDevice::CPU => wasi_nn::EXECUTION_TARGET_CPU,
+            InferenceGraphDevice::GPU => wasi_nn::EXECUTION_TARGET_GPU,
+            InferenceGraphDevice::TPU => wasi_nn::EXECUTION_TARGET_TPU,
+        }
+    }
+}
+
+/// the raw functions of ```wasi_ephemeral_nn``` which cannot be used by the wasi-nn crate:
+/// the crate has no value for the WasmEdge encodings, and no ```load_by_name```.
+mod raw {
+    #[link(wasm_import_module = "wasi_ephemeral_nn")]
+    extern "C" {
+        pub fn load(
+            builder_ptr: i32,
+            builder_len: i32,
+            encoding: i32,
+            target: i32,
+            graph_ptr: i32,
+        ) -> i32;
+
+        #[cfg(feature = "wasi-nn-load-by-name")]
+        pub fn load_by_name(name_ptr: i32, name_len: i32, graph_ptr: i32) -> i32;
+    }
+}
+
+impl From<InferenceTensorType> for wasi_nn::TensorType {
+    fn from(value: InferenceTensorType) -> Self {
+        match value {
+            InferenceTensorType::F16 => wasi_nn::TENSOR_TYPE_F16,
+            InferenceTensorType::F32 => wasi_nn::TENSOR_TYPE_F32,
+            InferenceTensorType::U8 => wasi_nn::TENSOR_TYPE_U8,
+            InferenceTensorType::I32 => wasi_nn::TENSOR_TYPE_I32,
+            // wasi-nn 0.2 has no i8 type, the backend only checks the bytes size
+            InferenceTensorType::I8 => wasi_nn::TENSOR_TYPE_U8,
+        }
+    }
+}
+
+/// WasiNn Backend
+/// do inference using the wasi-nn interface, it can only run in the wasi-nn host (such as WasmEdge)
+///
+/// note: wasi-nn 0.2 has no interface to release graphs and execution contexts,
+/// so ```drop_graph``` and ```drop_execution_context``` do nothing now.
+#[derive(Debug, Clone, Default)]
+pub struct WasiNnBackend;
+
+impl InferenceBackend for WasiNnBackend {
+    #[inline]
+    fn load(
+        &self,
+        builders: &[&[u8]],
+        encoding: InferenceGraphEncoding,
+        device: InferenceGraphDevice,
+    ) -> Result<InferenceGraphHandle, InferenceError> {
+        let target = wasi_nn::ExecutionTarget::from(device);
+        let encoding = match WasiNnEncoding::from(&encoding) {
+            WasiNnEncoding::Crate(encoding) => {
+                return unsafe { wasi_nn::load(builders, encoding, target) }
+                    .map_err(InferenceError::from);
+            }
+            WasiNnEncoding::WasmEdge(encoding) => encoding,
+        };
+
+        // the builders array is an array of (ptr, len) in wasm32, same as ```wasi_nn::load```
+        let mut graph: InferenceGraphHandle = 0;
+        let errno = unsafe {
+            raw::load(
+                builders.as_ptr() as i32,
+                builders.len() as i32,
+                encoding as i32,
+                target.raw() as i32,
+                &mut graph as *mut InferenceGraphHandle as i32,
+            )
+        };
+        match errno {
+            0 => Ok(graph),
+            _ => Err(InferenceError::from_errno(errno as u16)),
+        }
+    }
+
+    #[cfg(feature = "wasi-nn-load-by-name")]
+    fn load_by_name(&self, name: &str) -> Result<InferenceGraphHandle, InferenceError> {
+        let mut graph: InferenceGraphHandle = 0;
+        let errno = unsafe {
+            raw::load_by_name(
+                name.as_ptr() as i32,
+                name.len() as i32,
+                &mut graph as *mut InferenceGraphHandle as i32,
+            )
+        };
+        match errno {
+            0 => Ok(graph),
+            _ => Err(InferenceError::from_errno(errno as u16)),
+        }
+    }
+
+    #[inline]
+    fn init_execution_context(
+        &self,
+        graph: InferenceGraphHandle,
+    ) -> Result<InferenceExecutionContextHandle, InferenceError> {
+        unsafe { wasi_nn::init_execution_context(graph) }.map_err(InferenceError::from)
+    }
+
+    #[inline]
+    fn set_input(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        tensor: &InferenceTensor,
+    ) -> Result<(), InferenceError> {
+        let (shape, data) = tensor.tensor_data_ref();
+        let tensor = wasi_nn::Tensor {
+            dimensions: shape,
+            type_: tensor.tp().into(),
+            data,
+        };
+        unsafe { wasi_nn::set_input(ctx, index, tensor) }.map_err(InferenceError::from)
+    }
+
+    #[inline(always)]
+    fn compute(&self, ctx: InferenceExecutionContextHandle) -> Result<(), InferenceError> {
+        unsafe { wasi_nn::compute(ctx) }.map_err(InferenceError::from)
+    }
+
+    #[inline]
+    fn get_output(
+        &self,
+        ctx: InferenceExecutionContextHandle,
+        index: u32,
+        buf: &mut [u8],
+    ) -> Result<u32, InferenceError> {
+        unsafe { wasi_nn::get_output(ctx, index, buf.as_mut_ptr(), buf.len() as u32) }
+            .map_err(InferenceError::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encoding_mapping() {
+        assert_eq!(
+            WasiNnEncoding::from(&InferenceGraphEncoding::Onnx),
+            WasiNnEncoding::Crate(wasi_nn::GRAPH_ENCODING_ONNX)
+        );
+        assert_eq!(
+            WasiNnEncoding::from(&InferenceGraphEncoding::Pytorch),
+            WasiNnEncoding::Crate(wasi_nn::GRAPH_ENCODING_PYTORCH)
+        );
+        assert_eq!(
+            WasiNnEncoding::from(&InferenceGraphEncoding::TensorflowLite),
+            WasiNnEncoding::WasmEdge(4)
+        );
+        assert_eq!(
+            wasi_nn::ExecutionTarget::from(InferenceGraphDevice::GPU),
+            wasi_nn::EXECUTION_TARGET_GPU
+        );
+    }
+
+    #[cfg(feature = "wasi-nn-extra-encodings")]
+    #[test]
+    fn test_extra_encoding_mapping() {
+        assert_eq!(
+            WasiNnEncoding::from(&InferenceGraphEncoding::Autodetect),
+            WasiNnEncoding::WasmEdge(5)
+        );
+        assert_eq!(
+            WasiNnEncoding::from(&InferenceGraphEncoding::Ggml),
+            WasiNnEncoding::WasmEdge(6)
+        );
+    }
+
+    #[test]
+    fn test_errno_mapping() {
+        let e = InferenceError::from(wasi_nn::NN_ERRNO_BUSY);
+        assert!(matches!(e, InferenceError::WasiNnError { errno: 4, .. }));
+        assert!(matches!(
+            std::error::Error::source(&e).and_then(|s| s.downcast_ref::<InferenceError>()),
+            Some(InferenceError::Busy)
+        ));
+        assert!(matches!(
+            InferenceError::from_errno(100),
+            InferenceError::WasiNnError {
+                errno: 100,
+                source: None
+            }
+        ));
+    }
+}
@@ -1,3 +1,15 @@
+/// the error type of this crate
+mod error;
+pub use error::*;
+
+/// find and read the model files
+mod model_locator;
+pub use model_locator::*;
+
+/// record the durations of the processing stages
+mod profiler;
+pub use profiler::*;
+
 /// process the media input to tensor
 pub mod preprocess;
 
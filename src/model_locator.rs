@@ -0,0 +1,254 @@
+use crate::error::{Error, Result};
+use crate::inference::{InferenceError, InferenceGraph, InferenceGraphBuilder};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// the environment variable of model directories, the directories are searched first.
+/// use the platform path separator (```:``` on unix) for several directories.
+pub const MODEL_DIR_ENV: &str = "MEDIAPIPE_MODEL_DIR";
+
+/// the sha256 of the models which are known good,
+/// the quantized short range model is added when it is in the assets
+const KNOWN_MODELS: &[(&str, &str)] = &[(
+    "face_detection_short_range.tflite",
+    "bbff11cebd1eb27a1e004cae0b0e63ec8c551cbf34a4451148b4908b8db3eca8",
+)];
+
+/// where to load a model
+#[derive(Debug, Clone)]
+pub enum ModelSource {
+    /// an explicit file path
+    Path(PathBuf),
+    /// the model content in memory
+    Bytes(Vec<u8>),
+    /// a file name which is searched in the directories of [`ModelLocator`]
+    Name(String),
+}
+
+impl ModelSource {
+    /// the description used in error messages
+    fn display_path(&self) -> PathBuf {
+        match self {
+            ModelSource::Path(path) => path.clone(),
+            ModelSource::Bytes(_) => PathBuf::from("<memory>"),
+            ModelSource::Name(name) => PathBuf::from(name),
+        }
+    }
+}
+
+/// Model Locator
+/// find and read the model files, and verify the sha256 of the known models.
+///
+/// the directories are searched in order: the directories in environment variable
+/// [`MODEL_DIR_ENV`] (can be changed by ```env_var```), then the search paths.
+/// the default locator searches ```./assets``` and ```.```.
+///
+/// ### Examples
+///
+/// ```no_run
+/// use mediapipe_wasinn_demo::{ModelLocator, ModelSource};
+/// use mediapipe_wasinn_demo::inference::InferenceGraphBuilder;
+///
+/// let locator = ModelLocator::new().search_path("/models");
+/// let graph = locator.build_graph(
+///     &ModelSource::Name("face_detection_short_range.tflite".into()),
+///     InferenceGraphBuilder::default(),
+/// )?;
+/// # Ok::<(), mediapipe_wasinn_demo::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelLocator {
+    search_paths: Vec<PathBuf>,
+    env_var: Option<String>,
+    /// file name -> sha256 hex string
+    known_hashes: HashMap<String, String>,
+}
+
+impl Default for ModelLocator {
+    fn default() -> Self {
+        Self::new().search_path("./assets").search_path(".")
+    }
+}
+
+impl ModelLocator {
+    /// create a locator without search paths, the known models hashes are used.
+    pub fn new() -> Self {
+        Self {
+            search_paths: Vec::new(),
+            env_var: Some(MODEL_DIR_ENV.to_string()),
+            known_hashes: KNOWN_MODELS
+                .iter()
+                .map(|(name, hash)| (name.to_string(), hash.to_string()))
+                .collect(),
+        }
+    }
+
+    /// append a directory to search paths
+    #[inline]
+    pub fn search_path(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.search_paths.push(dir.into());
+        self
+    }
+
+    /// set the environment variable of model directories, ```None``` to disable it.
+    #[inline]
+    pub fn env_var(mut self, name: Option<&str>) -> Self {
+        self.env_var = name.map(|n| n.to_string());
+        self
+    }
+
+    /// set the expected sha256 (hex string) of the model file name,
+    /// the file with this name will be verified when loading.
+    #[inline]
+    pub fn known_hash(mut self, file_name: impl Into<String>, sha256: impl Into<String>) -> Self {
+        self.known_hashes
+            .insert(file_name.into(), sha256.into().to_ascii_lowercase());
+        self
+    }
+
+    /// all directories to search, in order
+    pub fn search_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = self.env_dirs();
+        dirs.extend(self.search_paths.iter().cloned());
+        dirs
+    }
+
+    /// the directories in the environment variable, they override the other model sources
+    pub fn env_dirs(&self) -> Vec<PathBuf> {
+        match self.env_var.as_ref().and_then(std::env::var_os) {
+            Some(value) => std::env::split_paths(&value)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// find the file in the search directories
+    pub fn locate(&self, name: &str) -> Result<PathBuf> {
+        let dirs = self.search_dirs();
+        dirs.iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                let msg = format!("model not found in {:?}", dirs);
+                Error::model_load(
+                    name,
+                    std::io::Error::new(std::io::ErrorKind::NotFound, msg).into(),
+                )
+            })
+    }
+
+    /// read the model content, the known models are verified.
+    /// return the path (for error messages) and the content.
+    pub fn read(&self, source: &ModelSource) -> Result<(PathBuf, Vec<u8>)> {
+        let path = match source {
+            ModelSource::Bytes(bytes) => return Ok((source.display_path(), bytes.clone())),
+            ModelSource::Path(path) => path.clone(),
+            ModelSource::Name(name) => self.locate(name)?,
+        };
+        let content = std::fs::read(&path)
+            .map_err(|e| Error::model_load(&path, InferenceError::io_error(&path, e)))?;
+        self.verify(&path, &content)?;
+        Ok((path, content))
+    }
+
+    /// load the model and build the graph
+    pub fn build_graph(
+        &self,
+        source: &ModelSource,
+        builder: InferenceGraphBuilder,
+    ) -> Result<InferenceGraph> {
+        let (path, content) = self.read(source)?;
+        builder
+            .build_from_bytes(content)
+            .map_err(|e| Error::model_load(path, e))
+    }
+
+    /// check the sha256 if the file name is known
+    fn verify(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let expect = match path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| self.known_hashes.get(n))
+        {
+            Some(expect) => expect,
+            None => return Ok(()),
+        };
+        let got = sha256_hex(content);
+        if &got != expect {
+            return Err(Error::ModelHashMismatch {
+                path: path.to_path_buf(),
+                expect: expect.clone(),
+                got,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// the sha256 of data as lowercase hex string
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MODEL: &str = "face_detection_short_range.tflite";
+
+    #[test]
+    fn test_locate_and_verify() {
+        let locator = ModelLocator::new()
+            .env_var(None)
+            .search_path("./not_exists")
+            .search_path("./assets");
+        let (path, content) = locator.read(&ModelSource::Name(MODEL.into())).unwrap();
+        assert_eq!(path, Path::new("./assets").join(MODEL));
+        assert_eq!(sha256_hex(&content), KNOWN_MODELS[0].1);
+
+        // not found
+        let err = ModelLocator::new()
+            .env_var(None)
+            .read(&ModelSource::Name(MODEL.into()))
+            .unwrap_err();
+        assert!(matches!(err, Error::ModelLoad { .. }));
+
+        // hash mismatch
+        let err = locator
+            .clone()
+            .known_hash(MODEL, "00".repeat(32))
+            .read(&ModelSource::Path(path))
+            .unwrap_err();
+        assert!(matches!(err, Error::ModelHashMismatch { .. }));
+
+        // bytes are not verified
+        let (_, bytes) = locator.read(&ModelSource::Bytes(vec![1, 2])).unwrap();
+        assert_eq!(bytes, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_env_override() {
+        // a unique directory, so the concurrent test runs do not share it
+        let dir = std::env::temp_dir().join(format!(
+            "mediapipe_wasinn_locator_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("model.bin"), [0u8; 4]).unwrap();
+
+        // a unique variable name, so other tests are not affected
+        let env = "MEDIAPIPE_MODEL_DIR_LOCATOR_TEST";
+        let locator = ModelLocator::default().env_var(Some(env));
+        assert!(locator.locate("model.bin").is_err());
+        std::env::set_var(env, &dir);
+        assert_eq!(locator.locate("model.bin").unwrap(), dir.join("model.bin"));
+        assert_eq!(locator.search_dirs()[0], dir);
+        std::env::remove_var(env);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
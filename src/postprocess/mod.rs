@@ -3,6 +3,7 @@ pub mod draw_utils;
 pub mod ops;
 mod ssd_anchors_generator;
 
+use crate::preprocess::{AffineTransform, LetterboxPadding};
 pub use detection::*;
 pub use ssd_anchors_generator::*;
 use std::fmt::Debug;
@@ -21,6 +22,24 @@ impl Pointer2D<f32> {
             y: self.y / y_scale * anchor.h + anchor.y_center,
         }
     }
+
+    /// map the normalized point in the letterboxed image to the original image,
+    /// like MediaPipe ```DetectionLetterboxRemovalCalculator```
+    #[inline]
+    pub fn remove_letterbox(self, padding: &LetterboxPadding) -> Self {
+        Self {
+            x: padding.remove_x(self.x),
+            y: padding.remove_y(self.y),
+        }
+    }
+
+    /// project the normalized point in the roi crop to the original image,
+    /// ```transform``` is returned by ```ImageToTensor::convert_roi```
+    #[inline]
+    pub fn project(self, transform: &AffineTransform) -> Self {
+        let (x, y) = transform.apply(self.x, self.y);
+        Self { x, y }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -42,3 +61,15 @@ impl<T: Debug + Clone> Box2D<T> {
         }
     }
 }
+
+impl Box2D<f32> {
+    /// map the normalized box in the letterboxed image to the original image
+    #[inline]
+    pub fn remove_letterbox(self, padding: &LetterboxPadding) -> Self {
+        Self {
+            p: self.p.remove_letterbox(padding),
+            w: padding.remove_width(self.w),
+            h: padding.remove_height(self.h),
+        }
+    }
+}
@@ -1,34 +1,222 @@
 use super::*;
-use crate::inference::{InferenceTensorDataLayout as DataLayout, InferenceTensorType};
-use image::RgbImage;
+use crate::inference::{InferenceTensorDataLayout as DataLayout, InferenceTensorQuantization};
+use image::{GrayImage, RgbImage};
+
+const NO_QUANTIZATION: InferenceTensorQuantization = InferenceTensorQuantization {
+    scale: 1.0,
+    zero_point: 0,
+};
 
 /// RgbImage generate a tensor
 /// before generate tensor, the image must do resize!
 ///
+/// NHWC is the interleaved pixels, NCHW and CHWN are the planar channels,
+/// they have the same data for one image and only the shapes are different.
+/// ```Unknown``` keeps the interleaved pixels (same as NHWC).
+///
 /// Image Preprocess Reference:
 /// NHWC for tflite: https://github.com/tensorflow/models/blob/4fcd44d71eb15c1c17612bf6cefc646caaf671f1/research/slim/preprocessing/inception_preprocessing.py#L258
 ///
 impl ToTensor for RgbImage {
-    fn to_tensor(&self, data_layout: DataLayout) -> InferenceTensor<'static> {
-        const MULTIPLY: f32 = 2.0f32 / 255.0f32;
+    fn to_tensor_with_options_into(
+        &self,
+        options: &TensorConversionOptions,
+        shape: &mut Vec<u32>,
+        out: &mut Vec<u8>,
+    ) {
+        let quantization = options.quantization.as_ref().unwrap_or(&NO_QUANTIZATION);
+        let table = options.normalization.lookup_table();
+        let channels = options.channel_order.source_channels();
+        let (h, w) = (self.height(), self.width());
+        shape.clear();
+        out.clear();
+        out.reserve((h * w * 3) as usize * options.tp.byte_size());
 
-        match data_layout {
-            DataLayout::NHWC => {
-                let mut data = Vec::with_capacity((self.height() * self.width() * 3 * 4) as usize);
-                for p in self.as_ref() {
-                    let f = (*p as f32) * MULTIPLY - 1.0f32;
-                    for b in f.to_ne_bytes() {
-                        data.push(b);
-                    }
+        let raw = self.as_raw();
+        match options.layout {
+            DataLayout::NHWC | DataLayout::Unknown => {
+                let values = raw
+                    .chunks_exact(3)
+                    .flat_map(|px| (0..3).map(move |c| (c, px[channels[c]])))
+                    .map(|(c, p)| table[c][p as usize]);
+                quantize_to_bytes(values, &options.tp, quantization, out);
+                shape.extend([1, h, w, 3]);
+            }
+            DataLayout::NCHW | DataLayout::CHWN => {
+                let values = (0..3).flat_map(|c| {
+                    raw[channels[c]..]
+                        .iter()
+                        .step_by(3)
+                        .map(move |p| table[c][*p as usize])
+                });
+                quantize_to_bytes(values, &options.tp, quantization, out);
+                match options.layout {
+                    DataLayout::NCHW => shape.extend([1, 3, h, w]),
+                    _ => shape.extend([3, h, w, 1]),
                 }
-                InferenceTensor::new(
-                    InferenceTensorType::F32,
-                    data_layout,
-                    vec![1, self.height(), self.width(), 3],
-                    data,
-                )
             }
-            _ => unimplemented!(),
         }
     }
 }
+
+/// only the raw NHWC data can be used without copying
+impl ToTensorRef for RgbImage {
+    fn tensor_ref_data(&self, data_layout: &DataLayout, shape: &mut Vec<u32>) -> Option<&[u8]> {
+        match data_layout {
+            DataLayout::NHWC | DataLayout::Unknown => {
+                shape.clear();
+                shape.extend([1, self.height(), self.width(), 3]);
+                Some(self.as_raw())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// GrayImage generate a tensor with one channel, the channel order is ignored
+impl ToTensor for GrayImage {
+    fn to_tensor_with_options_into(
+        &self,
+        options: &TensorConversionOptions,
+        shape: &mut Vec<u32>,
+        out: &mut Vec<u8>,
+    ) {
+        let quantization = options.quantization.as_ref().unwrap_or(&NO_QUANTIZATION);
+        let table = options.normalization.lookup_table();
+        out.clear();
+        out.reserve(self.as_raw().len() * options.tp.byte_size());
+        quantize_to_bytes(
+            self.as_raw().iter().map(|p| table[0][*p as usize]),
+            &options.tp,
+            quantization,
+            out,
+        );
+        shape.clear();
+        shape.extend(gray_shape(self, &options.layout));
+    }
+}
+
+/// the data of one channel is same for all layouts
+impl ToTensorRef for GrayImage {
+    fn tensor_ref_data(&self, data_layout: &DataLayout, shape: &mut Vec<u32>) -> Option<&[u8]> {
+        shape.clear();
+        shape.extend(gray_shape(self, data_layout));
+        Some(self.as_raw())
+    }
+}
+
+#[inline]
+fn gray_shape(img: &GrayImage, data_layout: &DataLayout) -> [u32; 4] {
+    let (h, w) = (img.height(), img.width());
+    match data_layout {
+        DataLayout::NHWC | DataLayout::CHWN | DataLayout::Unknown => [1, h, w, 1],
+        DataLayout::NCHW => [1, 1, h, w],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inference::InferenceTensorType;
+
+    #[test]
+    fn test_rgb_image_planar_layouts() {
+        let img = RgbImage::from_fn(5, 3, |x, y| {
+            image::Rgb([(x * 50) as u8, (y * 100) as u8, (x + y) as u8])
+        });
+        let nhwc = img.to_tensor(DataLayout::NHWC);
+
+        let nchw = img.to_tensor(DataLayout::NCHW);
+        assert_eq!(nchw.shape_ref(), &[1, 3, 3, 5]);
+        let expect = nhwc.transpose_to(DataLayout::NCHW).unwrap();
+        assert_eq!(nchw.data_ref(), expect.data_ref());
+        // the planes are red, green and blue
+        let values = nchw.to_vec::<f32>().unwrap();
+        assert_eq!(values[1], Normalization::MinusOneToOne.apply(50, 0));
+        assert_eq!(values[15 + 5], Normalization::MinusOneToOne.apply(100, 1));
+
+        let quantization = InferenceTensorQuantization {
+            scale: 1.0 / 128.0,
+            zero_point: 128,
+        };
+        let chwn =
+            img.to_quantized_tensor(DataLayout::CHWN, InferenceTensorType::U8, &quantization);
+        assert_eq!(chwn.shape_ref(), &[3, 3, 5, 1]);
+        let expect = img
+            .to_quantized_tensor(DataLayout::NHWC, InferenceTensorType::U8, &quantization)
+            .transpose_to(DataLayout::CHWN)
+            .unwrap();
+        assert_eq!(chwn.data_ref(), expect.data_ref());
+    }
+
+    #[test]
+    fn test_rgb_image_conversion_options() {
+        let img = RgbImage::from_fn(2, 1, |x, _| image::Rgb([10 + x as u8, 100, 200]));
+
+        // raw uint8 in BGR order
+        let options =
+            TensorConversionOptions::raw_u8(DataLayout::NHWC).channel_order(ChannelOrder::Bgr);
+        let tensor = img.to_tensor_with_options(&options);
+        assert_eq!(tensor.tp(), InferenceTensorType::U8);
+        assert_eq!(tensor.data_ref(), &[200, 100, 10, 200, 100, 11]);
+
+        // planar with per-channel mean and std
+        let options = TensorConversionOptions::default()
+            .layout(DataLayout::NCHW)
+            .normalization(Normalization::IMAGENET);
+        let values = img
+            .to_tensor_with_options(&options)
+            .to_vec::<f32>()
+            .unwrap();
+        let expect = [(10, 0), (11, 0), (100, 1), (100, 1), (200, 2), (200, 2)]
+            .map(|(p, c)| Normalization::IMAGENET.apply(p, c));
+        assert_eq!(values, expect);
+
+        // the default options are same as ```to_tensor```
+        assert_eq!(
+            img.to_tensor_with_options(&TensorConversionOptions::default())
+                .data_ref(),
+            img.to_tensor(DataLayout::NHWC).data_ref()
+        );
+    }
+
+    #[test]
+    fn test_tensor_ref_and_scratch() {
+        let img = RgbImage::from_fn(4, 2, |x, y| image::Rgb([x as u8, y as u8, 9]));
+        let mut shape = Vec::new();
+        let tensor = img.to_tensor_ref(DataLayout::NHWC, &mut shape).unwrap();
+        assert_eq!(tensor.shape_ref(), &[1, 2, 4, 3]);
+        assert_eq!(tensor.data_ref().as_ptr(), img.as_raw().as_ptr());
+        assert!(img.to_tensor_ref(DataLayout::NCHW, &mut shape).is_none());
+
+        let gray = GrayImage::from_fn(4, 2, |x, _| image::Luma([x as u8]));
+        let tensor = gray.to_tensor_ref(DataLayout::NCHW, &mut shape).unwrap();
+        assert_eq!(tensor.shape_ref(), &[1, 1, 2, 4]);
+        assert_eq!(tensor.data_ref(), gray.as_raw().as_slice());
+
+        let mut scratch = TensorScratch::new();
+        // raw uint8: no copy
+        let options = TensorConversionOptions::raw_u8(DataLayout::NHWC);
+        let tensor = scratch.tensor(&img, &options);
+        assert_eq!(tensor.data_ref().as_ptr(), img.as_raw().as_ptr());
+        assert_eq!(scratch.capacity(), 0);
+
+        // converted to the scratch, and the buffer is reused
+        let options = TensorConversionOptions::default();
+        let tensor = scratch.tensor(&img, &options);
+        assert_eq!(
+            tensor.data_ref(),
+            img.to_tensor(DataLayout::NHWC).data_ref()
+        );
+        let ptr = tensor.data_ref().as_ptr();
+        let tensor = scratch.tensor(&img, &options);
+        assert_eq!(tensor.data_ref().as_ptr(), ptr);
+
+        let tensor = scratch.tensor(&gray, &options.layout(DataLayout::NCHW));
+        assert_eq!(tensor.shape_ref(), &[1, 1, 2, 4]);
+        assert_eq!(
+            tensor.to_vec::<f32>().unwrap()[..4],
+            [0, 1, 2, 3].map(|p| Normalization::MinusOneToOne.apply(p, 0))
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use super::{fit_dimensions, ImageResizer, TensorConversionOptions, ToTensor};
+use crate::inference::InferenceTensor;
+use image::RgbImage;
+
+/// the normalized paddings (0 ~ 1, relative to the tensor size) which are added by letterboxing,
+/// same as the ```letterbox_padding``` of MediaPipe ```ImageToTensorCalculator```.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LetterboxPadding {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl LetterboxPadding {
+    /// whether there is no padding
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// map a normalized x of the tensor to the normalized x of the original image
+    #[inline]
+    pub fn remove_x(&self, x: f32) -> f32 {
+        (x - self.left) / (1.0 - self.left - self.right)
+    }
+
+    /// map a normalized y of the tensor to the normalized y of the original image
+    #[inline]
+    pub fn remove_y(&self, y: f32) -> f32 {
+        (y - self.top) / (1.0 - self.top - self.bottom)
+    }
+
+    /// map a normalized width of the tensor to the normalized width of the original image
+    #[inline]
+    pub fn remove_width(&self, w: f32) -> f32 {
+        w / (1.0 - self.left - self.right)
+    }
+
+    /// map a normalized height of the tensor to the normalized height of the original image
+    #[inline]
+    pub fn remove_height(&self, h: f32) -> f32 {
+        h / (1.0 - self.top - self.bottom)
+    }
+}
+
+/// a rotated rectangle in the image, same as MediaPipe ```NormalizedRect```.
+/// the center and size are normalized (0 ~ 1) by the image width and height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizedRect {
+    pub x_center: f32,
+    pub y_center: f32,
+    pub width: f32,
+    pub height: f32,
+    /// the clockwise rotation in radians around the center
+    pub rotation: f32,
+}
+
+impl Default for NormalizedRect {
+    /// the whole image
+    fn default() -> Self {
+        Self {
+            x_center: 0.5,
+            y_center: 0.5,
+            width: 1.0,
+            height: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+/// how the pixels outside the image are sampled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BorderMode {
+    /// zero (black) pixels
+    #[default]
+    Zero,
+    /// the nearest edge pixels
+    Replicate,
+}
+
+/// a 2D affine transform of normalized points: ```x' = m[0] * x + m[1] * y + m[2]```
+/// and ```y' = m[3] * x + m[4] * y + m[5]```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AffineTransform {
+    pub matrix: [f32; 6],
+}
+
+impl Default for AffineTransform {
+    fn default() -> Self {
+        Self {
+            matrix: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+        }
+    }
+}
+
+impl AffineTransform {
+    #[inline]
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let m = &self.matrix;
+        (m[0] * x + m[1] * y + m[2], m[3] * x + m[4] * y + m[5])
+    }
+
+    /// the inverse transform, ```None``` if the matrix is singular
+    pub fn inverse(&self) -> Option<Self> {
+        let [a, b, c, d, e, f] = self.matrix;
+        let det = a * e - b * d;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(Self {
+            matrix: [
+                e / det,
+                -b / det,
+                (b * f - c * e) / det,
+                -d / det,
+                a / det,
+                (c * d - a * f) / det,
+            ],
+        })
+    }
+}
+
+/// Image To Tensor
+/// resize the image to the tensor size, like MediaPipe ```ImageToTensorCalculator```.
+///
+/// if ```keep_aspect_ratio``` is true (default), the image is resized to fit in the tensor size
+/// and padded with zero (black) pixels at both sides, the returned [`LetterboxPadding`] is used to
+/// map the results back to the original image (like MediaPipe ```DetectionLetterboxRemovalCalculator```).
+/// otherwise the image is stretched to the tensor size.
+///
+/// ```convert_roi``` crops a rotated rectangle (such as the region around a detection for
+/// the landmark models) by affine warping with bilinear sampling, the pixels outside the image
+/// are sampled by ```border_mode```. ```convert_roi_to_tensor``` also converts the crop to a tensor.
+///
+/// the converter owns the buffers, so the frames with the same size do not allocate memory.
+///
+/// ### Examples
+///
+/// ```
+/// use mediapipe_wasinn_demo::inference::InferenceTensorDataLayout;
+/// use mediapipe_wasinn_demo::preprocess::{ImageToTensor, NormalizedRect, ToTensor};
+/// # let frame = image::RgbImage::new(640, 480);
+/// # let (x, landmark_x, landmark_y) = (0.5, 0.5, 0.5);
+///
+/// let mut image_to_tensor = ImageToTensor::new(128, 128);
+/// let (img, padding) = image_to_tensor.convert(&frame);
+/// let tensor = img.to_tensor(InferenceTensorDataLayout::NHWC);
+/// // after inference, map the normalized results
+/// let x = padding.remove_x(x);
+///
+/// // crop a rotated region, and project the landmarks back to the image
+/// let roi = NormalizedRect { x_center: 0.4, y_center: 0.6, width: 0.3, height: 0.3, rotation: 0.5 };
+/// let (img, transform) = image_to_tensor.convert_roi(&frame, &roi);
+/// let (x, y) = transform.apply(landmark_x, landmark_y);
+/// ```
+#[derive(Debug)]
+pub struct ImageToTensor {
+    width: u32,
+    height: u32,
+    keep_aspect_ratio: bool,
+    border_mode: BorderMode,
+    resizer: ImageResizer,
+    resized: RgbImage,
+    output: RgbImage,
+}
+
+impl ImageToTensor {
+    /// the output size is ```width``` x ```height```
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            keep_aspect_ratio: true,
+            border_mode: BorderMode::Zero,
+            resizer: ImageResizer::new(),
+            resized: RgbImage::new(0, 0),
+            output: RgbImage::new(width, height),
+        }
+    }
+
+    #[inline]
+    pub fn keep_aspect_ratio(mut self, keep_aspect_ratio: bool) -> Self {
+        self.keep_aspect_ratio = keep_aspect_ratio;
+        self
+    }
+
+    /// the border mode of ```convert_roi```
+    #[inline]
+    pub fn border_mode(mut self, border_mode: BorderMode) -> Self {
+        self.border_mode = border_mode;
+        self
+    }
+
+    #[inline(always)]
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// resize (and pad) the image to the output size,
+    /// return the output image and the paddings in it.
+    pub fn convert(&mut self, image: &RgbImage) -> (&RgbImage, LetterboxPadding) {
+        let (width, height) = (self.width, self.height);
+        if !self.keep_aspect_ratio {
+            self.resizer.resize(image, &mut self.output);
+            return (&self.output, LetterboxPadding::default());
+        }
+
+        let (w, h) = fit_dimensions(image.width(), image.height(), width, height);
+        if (w, h) == (width, height) {
+            self.resizer.resize(image, &mut self.output);
+            return (&self.output, LetterboxPadding::default());
+        }
+        if self.resized.dimensions() != (w, h) {
+            self.resized = RgbImage::new(w, h);
+        }
+        self.resizer.resize(image, &mut self.resized);
+
+        // put the resized image at the center
+        let (left, top) = ((width - w) / 2, (height - h) / 2);
+        self.output.fill(0);
+        let row_len = w as usize * 3;
+        let output_row_len = width as usize * 3;
+        let output: &mut [u8] = &mut self.output;
+        for (y, row) in self.resized.as_raw().chunks_exact(row_len).enumerate() {
+            let start = (top as usize + y) * output_row_len + left as usize * 3;
+            output[start..start + row_len].copy_from_slice(row);
+        }
+
+        let padding = LetterboxPadding {
+            left: left as f32 / width as f32,
+            top: top as f32 / height as f32,
+            right: (width - w - left) as f32 / width as f32,
+            bottom: (height - h - top) as f32 / height as f32,
+        };
+        (&self.output, padding)
+    }
+
+    /// crop the rotated ```roi``` and warp it to the output size,
+    /// return the output image and the transform from the normalized points of the output
+    /// to the normalized points of ```image```, which is used to project the landmarks back.
+    ///
+    /// if ```keep_aspect_ratio``` is true, the roi is expanded to the aspect ratio of the output
+    /// (like MediaPipe), so the content is not stretched.
+    /// a roi with non-finite values (such as ```NaN``` from a failed detection) produces
+    /// a zero (black) image.
+    pub fn convert_roi(
+        &mut self,
+        image: &RgbImage,
+        roi: &NormalizedRect,
+    ) -> (&RgbImage, AffineTransform) {
+        let (img_w, img_h) = (image.width() as f32, image.height() as f32);
+        let (out_w, out_h) = (self.width as f32, self.height as f32);
+
+        // the roi size in pixels
+        let (mut roi_w, mut roi_h) = (roi.width * img_w, roi.height * img_h);
+        if self.keep_aspect_ratio && roi_w > 0.0 && roi_h > 0.0 {
+            if roi_w / roi_h > out_w / out_h {
+                roi_h = roi_w * out_h / out_w;
+            } else {
+                roi_w = roi_h * out_w / out_h;
+            }
+        }
+
+        // output (0 ~ 1) -> image (0 ~ 1)
+        let (sin, cos) = roi.rotation.sin_cos();
+        let (a, b) = (roi_w * cos / img_w, -roi_h * sin / img_w);
+        let (d, e) = (roi_w * sin / img_h, roi_h * cos / img_h);
+        let transform = AffineTransform {
+            matrix: [
+                a,
+                b,
+                roi.x_center - 0.5 * (a + b),
+                d,
+                e,
+                roi.y_center - 0.5 * (d + e),
+            ],
+        };
+
+        if image.width() == 0
+            || image.height() == 0
+            || !transform.matrix.iter().all(|v| v.is_finite())
+        {
+            self.output.fill(0);
+            return (&self.output, transform);
+        }
+
+        // sample the image at the centers of the output pixels
+        let m = &transform.matrix;
+        let border_mode = self.border_mode;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let output: &mut [u8] = &mut self.output;
+        for v in 0..height {
+            let t = (v as f32 + 0.5) / out_h;
+            for u in 0..width {
+                let s = (u as f32 + 0.5) / out_w;
+                let x = (m[0] * s + m[1] * t + m[2]) * img_w - 0.5;
+                let y = (m[3] * s + m[4] * t + m[5]) * img_h - 0.5;
+                let p = bilinear_sample(image, x, y, border_mode);
+                let start = (v * width + u) * 3;
+                output[start..start + 3].copy_from_slice(&p);
+            }
+        }
+        (&self.output, transform)
+    }
+
+    /// crop the rotated ```roi``` as ```convert_roi```, and convert the output to a tensor
+    /// by ```options```, return the tensor and the transform to project the landmarks back.
+    pub fn convert_roi_to_tensor(
+        &mut self,
+        image: &RgbImage,
+        roi: &NormalizedRect,
+        options: &TensorConversionOptions,
+    ) -> (InferenceTensor<'static>, AffineTransform) {
+        let (output, transform) = self.convert_roi(image, roi);
+        (output.to_tensor_with_options(options), transform)
+    }
+}
+
+/// sample the pixel at ```(x, y)```, the integer coordinates are the pixel centers.
+/// the coordinates far outside the image are saturated, so a huge roi does not overflow.
+fn bilinear_sample(image: &RgbImage, x: f32, y: f32, border_mode: BorderMode) -> [u8; 3] {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let (w, h) = (image.width() as i64, image.height() as i64);
+    let pixel = |px: i64, py: i64| -> Option<&[u8]> {
+        let (px, py) = match border_mode {
+            BorderMode::Zero if px < 0 || py < 0 || px >= w || py >= h => return None,
+            BorderMode::Zero => (px, py),
+            BorderMode::Replicate => (px.clamp(0, w - 1), py.clamp(0, h - 1)),
+        };
+        let start = ((py * w + px) * 3) as usize;
+        Some(&image.as_raw()[start..start + 3])
+    };
+
+    let mut out = [0f32; 3];
+    for (px, py, weight) in [
+        (x0, y0, (1.0 - fx) * (1.0 - fy)),
+        (x0.saturating_add(1), y0, fx * (1.0 - fy)),
+        (x0, y0.saturating_add(1), (1.0 - fx) * fy),
+        (x0.saturating_add(1), y0.saturating_add(1), fx * fy),
+    ] {
+        if weight == 0.0 {
+            continue;
+        }
+        if let Some(p) = pixel(px, py) {
+            out.iter_mut()
+                .zip(p)
+                .for_each(|(o, p)| *o += *p as f32 * weight);
+        }
+    }
+    out.map(|o| o.clamp(0.0, 255.0).round() as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_letterbox() {
+        let mut image_to_tensor = ImageToTensor::new(8, 8);
+
+        // 16x8 -> 8x4 at the center, 2 rows of padding at the top and bottom
+        let img = RgbImage::from_pixel(16, 8, image::Rgb([200, 100, 50]));
+        let (output, padding) = image_to_tensor.convert(&img);
+        assert_eq!(output.dimensions(), (8, 8));
+        assert_eq!(output.get_pixel(3, 1), &image::Rgb([0, 0, 0]));
+        assert_eq!(output.get_pixel(3, 2), &image::Rgb([200, 100, 50]));
+        assert_eq!(output.get_pixel(3, 5), &image::Rgb([200, 100, 50]));
+        assert_eq!(output.get_pixel(3, 6), &image::Rgb([0, 0, 0]));
+        assert_eq!(
+            padding,
+            LetterboxPadding {
+                left: 0.0,
+                top: 0.25,
+                right: 0.0,
+                bottom: 0.25,
+            }
+        );
+        // the top and bottom of the content are mapped to 0 and 1
+        assert_eq!(padding.remove_y(0.25), 0.0);
+        assert_eq!(padding.remove_y(0.75), 1.0);
+        assert_eq!(padding.remove_height(0.25), 0.5);
+        assert_eq!(padding.remove_x(0.5), 0.5);
+
+        // a square image is not padded
+        let (_, padding) = image_to_tensor.convert(&RgbImage::new(4, 4));
+        assert!(padding.is_empty());
+
+        // stretch
+        let mut image_to_tensor = ImageToTensor::new(8, 8).keep_aspect_ratio(false);
+        let (output, padding) = image_to_tensor.convert(&img);
+        assert!(padding.is_empty());
+        assert_eq!(output.get_pixel(3, 0), &image::Rgb([200, 100, 50]));
+
+        // an empty frame does not reuse the pixels of the previous frame
+        let (output, _) = image_to_tensor.convert(&RgbImage::new(0, 0));
+        assert!(output.as_raw().iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    fn test_convert_roi() {
+        let img = RgbImage::from_fn(4, 4, |x, y| image::Rgb([(x * 10 + y) as u8, 0, 255]));
+        let mut image_to_tensor = ImageToTensor::new(4, 4);
+
+        // the whole image is copied
+        let (output, transform) = image_to_tensor.convert_roi(&img, &NormalizedRect::default());
+        assert_eq!(output, &img);
+        assert_eq!(transform, AffineTransform::default());
+
+        // rotate 90 degrees clockwise: output (u, v) is image (3 - v, u)
+        let roi = NormalizedRect {
+            rotation: std::f32::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        let (output, transform) = image_to_tensor.convert_roi(&img, &roi);
+        for (u, v, p) in output.enumerate_pixels() {
+            assert_eq!(p, img.get_pixel(3 - v, u));
+        }
+        // the top left of the output is the top right of the image
+        let (x, y) = transform.apply(0.0, 0.0);
+        assert!((x - 1.0).abs() < 1e-6 && y.abs() < 1e-6);
+        let inverse = transform.inverse().unwrap();
+        let (u, v) = inverse.apply(x, y);
+        assert!(u.abs() < 1e-6 && v.abs() < 1e-6);
+
+        // the right half is expanded to a square, the last column is outside the image
+        let roi = NormalizedRect {
+            x_center: 0.75,
+            width: 0.5,
+            ..Default::default()
+        };
+        let (output, transform) = image_to_tensor.convert_roi(&img, &roi);
+        assert_eq!(transform.apply(1.0, 1.0), (1.25, 1.0));
+        for v in 0..4 {
+            assert_eq!(output.get_pixel(0, v), img.get_pixel(1, v));
+            assert_eq!(output.get_pixel(2, v), img.get_pixel(3, v));
+            assert_eq!(output.get_pixel(3, v), &image::Rgb([0, 0, 0]));
+        }
+
+        let mut image_to_tensor = ImageToTensor::new(4, 4).border_mode(BorderMode::Replicate);
+        let (output, _) = image_to_tensor.convert_roi(&img, &roi);
+        for v in 0..4 {
+            assert_eq!(output.get_pixel(3, v), img.get_pixel(3, v));
+        }
+
+        // stretched without keeping the aspect ratio
+        let mut image_to_tensor = ImageToTensor::new(2, 4).keep_aspect_ratio(false);
+        let (output, _) = image_to_tensor.convert_roi(&img, &roi);
+        assert_eq!(output.get_pixel(1, 2), img.get_pixel(3, 2));
+    }
+
+    #[test]
+    fn test_convert_roi_to_tensor() {
+        use crate::inference::{InferenceTensorDataLayout, InferenceTensorType};
+
+        let img = RgbImage::from_fn(4, 4, |x, y| image::Rgb([(x * 10 + y) as u8, 0, 255]));
+        let mut image_to_tensor = ImageToTensor::new(4, 4);
+        let roi = NormalizedRect {
+            rotation: std::f32::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        let (expect, expect_transform) = {
+            let (output, transform) = image_to_tensor.convert_roi(&img, &roi);
+            (output.clone(), transform)
+        };
+
+        let options = TensorConversionOptions::raw_u8(InferenceTensorDataLayout::NHWC);
+        let (tensor, transform) = image_to_tensor.convert_roi_to_tensor(&img, &roi, &options);
+        assert_eq!(transform, expect_transform);
+        assert_eq!(tensor.tp(), InferenceTensorType::U8);
+        assert_eq!(tensor.shape_ref(), &[1, 4, 4, 3]);
+        assert_eq!(tensor.data_ref(), expect.as_raw().as_slice());
+
+        let options = TensorConversionOptions::mediapipe(InferenceTensorDataLayout::NCHW);
+        let (tensor, _) = image_to_tensor.convert_roi_to_tensor(&img, &roi, &options);
+        assert_eq!(tensor.shape_ref(), &[1, 3, 4, 4]);
+        let value = tensor.to_vec::<f32>().unwrap()[0];
+        assert!((value - (expect.as_raw()[0] as f32 / 127.5 - 1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_convert_roi_invalid() {
+        let img = RgbImage::from_pixel(4, 4, image::Rgb([200, 100, 50]));
+        let black = RgbImage::new(4, 4);
+        for border_mode in [BorderMode::Zero, BorderMode::Replicate] {
+            let mut image_to_tensor = ImageToTensor::new(4, 4).border_mode(border_mode);
+
+            // non-finite values
+            for roi in [
+                NormalizedRect {
+                    x_center: f32::NAN,
+                    ..Default::default()
+                },
+                NormalizedRect {
+                    width: f32::INFINITY,
+                    ..Default::default()
+                },
+                NormalizedRect {
+                    rotation: f32::NAN,
+                    ..Default::default()
+                },
+            ] {
+                let (output, _) = image_to_tensor.convert_roi(&img, &roi);
+                assert_eq!(output, &black);
+            }
+
+            // huge but finite values are saturated
+            let roi = NormalizedRect {
+                x_center: 1e30,
+                y_center: -1e30,
+                width: 1e30,
+                height: 1e30,
+                rotation: 0.0,
+            };
+            let (output, _) = image_to_tensor.convert_roi(&img, &roi);
+            assert_eq!(output.dimensions(), (4, 4));
+        }
+    }
+}
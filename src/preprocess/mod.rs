@@ -1,15 +1,113 @@
 mod image_process;
+mod image_to_tensor;
+mod quantize;
+mod resize;
+mod tensor_conversion;
 
 // other: video stream process? audio process?
 
-use super::inference::{InferenceTensor, InferenceTensorDataLayout};
+pub use image_to_tensor::*;
+pub use quantize::*;
+pub use resize::*;
+pub use tensor_conversion::*;
+
+use super::inference::{
+    InferenceTensor, InferenceTensorDataLayout, InferenceTensorQuantization, InferenceTensorType,
+};
 
 /// use data to generate a tensor
+///
+/// only ```to_tensor_with_options_into``` is required, the other methods are built on it.
 pub trait ToTensor {
-    fn to_tensor(&self, data_layout: InferenceTensorDataLayout) -> InferenceTensor<'static>;
+    /// write the shape and the tensor data converted by ```options``` to ```shape``` and ```out```.
+    /// both are cleared first, so the buffers can be reused for every frame.
+    fn to_tensor_with_options_into(
+        &self,
+        options: &TensorConversionOptions,
+        shape: &mut Vec<u32>,
+        out: &mut Vec<u8>,
+    );
+
+    /// generate a tensor converted by ```options```
+    fn to_tensor_with_options(
+        &self,
+        options: &TensorConversionOptions,
+    ) -> InferenceTensor<'static> {
+        let mut shape = Vec::new();
+        let mut data = Vec::new();
+        self.to_tensor_with_options_into(options, &mut shape, &mut data);
+        InferenceTensor::new(options.tp.clone(), options.layout.clone(), shape, data)
+    }
+
+    /// write the shape and the tensor data of type ```tp``` to ```shape``` and ```out```,
+    /// the values are in [-1, 1] (see [`TensorConversionOptions::mediapipe`]).
+    /// the quantization parameters are only used for integer types (```None``` is scale 1 and zero point 0).
+    fn to_tensor_data_into(
+        &self,
+        data_layout: &InferenceTensorDataLayout,
+        tp: &InferenceTensorType,
+        quantization: Option<&InferenceTensorQuantization>,
+        shape: &mut Vec<u32>,
+        out: &mut Vec<u8>,
+    ) {
+        let options = TensorConversionOptions::mediapipe(data_layout.clone())
+            .tensor_type(tp.clone(), quantization.cloned());
+        self.to_tensor_with_options_into(&options, shape, out)
+    }
+
+    fn to_tensor(&self, data_layout: InferenceTensorDataLayout) -> InferenceTensor<'static> {
+        self.to_tensor_with_options(&TensorConversionOptions::mediapipe(data_layout))
+    }
+
+    /// generate a tensor for quantized models, the normalized values are quantized to ```tp```
+    /// with the quantization parameters of the model input.
+    fn to_quantized_tensor(
+        &self,
+        data_layout: InferenceTensorDataLayout,
+        tp: InferenceTensorType,
+        quantization: &InferenceTensorQuantization,
+    ) -> InferenceTensor<'static> {
+        self.to_tensor_with_options(
+            &TensorConversionOptions::mediapipe(data_layout)
+                .tensor_type(tp, Some(quantization.clone())),
+        )
+    }
+
+    /// generate a tensor for fp16 models, the values are same as ```to_tensor```
+    fn to_f16_tensor(&self, data_layout: InferenceTensorDataLayout) -> InferenceTensor<'static> {
+        self.to_tensor_with_options(
+            &TensorConversionOptions::mediapipe(data_layout)
+                .tensor_type(InferenceTensorType::F16, None),
+        )
+    }
 }
 
 /// use data to generate a tensor, and tensor data is a reference of a memory
+///
+/// only the raw uint8 data can be used without copying, use [`TensorScratch`] to convert
+/// to other types with a reusable buffer.
 pub trait ToTensorRef {
-    fn to_tensor_ref(&self, data_layout: InferenceTensorDataLayout) -> InferenceTensor;
+    /// the raw data, if the data can be used as a uint8 tensor of ```data_layout```
+    /// without copying, and the shape is written to ```shape``` (cleared first).
+    /// otherwise return ```None```.
+    fn tensor_ref_data(
+        &self,
+        data_layout: &InferenceTensorDataLayout,
+        shape: &mut Vec<u32>,
+    ) -> Option<&[u8]>;
+
+    /// a uint8 tensor which refers to the data, the shape is stored in ```shape```.
+    fn to_tensor_ref<'a>(
+        &'a self,
+        data_layout: InferenceTensorDataLayout,
+        shape: &'a mut Vec<u32>,
+    ) -> Option<InferenceTensor<'a>> {
+        let data = self.tensor_ref_data(&data_layout, shape)?;
+        Some(InferenceTensor::new_ref(
+            InferenceTensorType::U8,
+            data_layout,
+            shape,
+            data,
+        ))
+    }
 }
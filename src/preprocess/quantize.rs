@@ -0,0 +1,79 @@
+use crate::inference::{f16, InferenceTensorQuantization, InferenceTensorType};
+
+/// quantize the real values to ```tp``` and append the bytes to ```out```.
+/// the quantized values are clamped to the range of ```tp```,
+/// and the float types (```F32``` and ```F16```) are not quantized.
+pub fn quantize_to_bytes(
+    values: impl Iterator<Item = f32>,
+    tp: &InferenceTensorType,
+    quantization: &InferenceTensorQuantization,
+    out: &mut Vec<u8>,
+) {
+    match tp {
+        InferenceTensorType::F32 => values.for_each(|v| out.extend_from_slice(&v.to_ne_bytes())),
+        InferenceTensorType::U8 => values.for_each(|v| {
+            let q = quantization
+                .quantize(v)
+                .clamp(u8::MIN as i64, u8::MAX as i64);
+            out.push(q as u8);
+        }),
+        InferenceTensorType::I8 => values.for_each(|v| {
+            let q = quantization
+                .quantize(v)
+                .clamp(i8::MIN as i64, i8::MAX as i64);
+            out.push(q as i8 as u8);
+        }),
+        InferenceTensorType::I32 => values.for_each(|v| {
+            let q = quantization
+                .quantize(v)
+                .clamp(i32::MIN as i64, i32::MAX as i64);
+            out.extend_from_slice(&(q as i32).to_ne_bytes());
+        }),
+        InferenceTensorType::F16 => {
+            values.for_each(|v| out.extend_from_slice(&f16::from_f32(v).to_ne_bytes()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quantize_to_bytes() {
+        let q = InferenceTensorQuantization {
+            scale: 0.5,
+            zero_point: 10,
+        };
+        let values = [-10.0f32, -1.0, 0.0, 1.2, 200.0];
+
+        let mut out = Vec::new();
+        quantize_to_bytes(values.into_iter(), &InferenceTensorType::U8, &q, &mut out);
+        assert_eq!(out, vec![0, 8, 10, 12, 255]);
+
+        out.clear();
+        quantize_to_bytes(values.into_iter(), &InferenceTensorType::I8, &q, &mut out);
+        assert_eq!(out, vec![(-10i8) as u8, 8, 10, 12, 127]);
+        // dequantize is the inverse (for the values in range)
+        assert_eq!(q.dequantize(12), 1.0);
+
+        // f16 values are not quantized
+        out.clear();
+        quantize_to_bytes(values.into_iter(), &InferenceTensorType::F16, &q, &mut out);
+        assert_eq!(out.len(), values.len() * 2);
+        assert_eq!(out[6..8], f16::from_f32(1.2).to_ne_bytes());
+
+        // every tensor type is supported
+        for tp in [
+            InferenceTensorType::F16,
+            InferenceTensorType::F32,
+            InferenceTensorType::U8,
+            InferenceTensorType::I32,
+            InferenceTensorType::I8,
+        ] {
+            out.clear();
+            quantize_to_bytes(values.into_iter(), &tp, &q, &mut out);
+            assert_eq!(out.len(), values.len() * tp.byte_size());
+        }
+    }
+}
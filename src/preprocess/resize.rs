@@ -0,0 +1,147 @@
+use image::RgbImage;
+
+/// the size of ```(width, height)``` after resizing to fit in ```(max_width, max_height)```
+/// and keeping the aspect ratio, it is same as ```DynamicImage::resize```.
+pub fn fit_dimensions(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let ratio = f64::min(
+        max_width as f64 / width as f64,
+        max_height as f64 / height as f64,
+    );
+    let w = ((width as f64 * ratio).round() as u32).max(1);
+    let h = ((height as f64 * ratio).round() as u32).max(1);
+    (w, h)
+}
+
+/// Image Resizer
+/// resize images with the triangle (bilinear) filter, the results are same as
+/// ```image::imageops::resize``` with ```FilterType::Triangle``` (the rounding may differ by 1).
+///
+/// the resizer owns the temporary buffers, so resizing the frames of a video with the same size
+/// does not allocate memory after the first frame.
+#[derive(Debug, Default)]
+pub struct ImageResizer {
+    /// the result of vertical sampling, ```src_width * dst_height * 3```
+    tmp: Vec<f32>,
+    weights: Vec<f32>,
+}
+
+impl ImageResizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// resize ```src``` to the size of ```dst```, ```dst``` is filled with zero if ```src``` is empty
+    pub fn resize(&mut self, src: &RgbImage, dst: &mut RgbImage) {
+        let (src_w, src_h) = src.dimensions();
+        let (dst_w, dst_h) = dst.dimensions();
+        if (src_w, src_h) == (dst_w, dst_h) {
+            dst.copy_from_slice(src.as_raw());
+            return;
+        }
+        if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+            // do not keep the pixels of the previous frame
+            dst.fill(0);
+            return;
+        }
+
+        // vertical sampling: src_w x src_h -> src_w x dst_h
+        let row_len = src_w as usize * 3;
+        self.tmp.clear();
+        self.tmp.resize(row_len * dst_h as usize, 0.0);
+        for y in 0..dst_h {
+            let top = sample_weights(src_h, dst_h, y, &mut self.weights) as usize;
+            let out = &mut self.tmp[y as usize * row_len..(y as usize + 1) * row_len];
+            for (i, w) in self.weights.iter().enumerate() {
+                let row = &src.as_raw()[(top + i) * row_len..(top + i + 1) * row_len];
+                out.iter_mut()
+                    .zip(row)
+                    .for_each(|(o, p)| *o += *p as f32 * w);
+            }
+        }
+
+        // horizontal sampling: src_w x dst_h -> dst_w x dst_h
+        let dst_row_len = dst_w as usize * 3;
+        let dst_raw: &mut [u8] = dst;
+        for x in 0..dst_w as usize {
+            let left = sample_weights(src_w, dst_w, x as u32, &mut self.weights) as usize;
+            for y in 0..dst_h as usize {
+                let row = &self.tmp[y * row_len..(y + 1) * row_len];
+                let mut t = [0f32; 3];
+                for (i, w) in self.weights.iter().enumerate() {
+                    let p = &row[(left + i) * 3..(left + i) * 3 + 3];
+                    t.iter_mut().zip(p).for_each(|(t, p)| *t += p * w);
+                }
+                let out = &mut dst_raw[y * dst_row_len + x * 3..y * dst_row_len + x * 3 + 3];
+                out.iter_mut()
+                    .zip(t)
+                    .for_each(|(o, t)| *o = t.clamp(0.0, 255.0).round() as u8);
+            }
+        }
+    }
+}
+
+/// compute the normalized triangle filter weights of the output pixel ```out_i```,
+/// return the index of the first input pixel.
+fn sample_weights(in_len: u32, out_len: u32, out_i: u32, weights: &mut Vec<f32>) -> u32 {
+    let ratio = in_len as f32 / out_len as f32;
+    let sratio = ratio.max(1.0);
+    // the support of triangle filter is 1
+    let support = sratio;
+
+    let center = (out_i as f32 + 0.5) * ratio;
+    let left = ((center - support).floor() as i64).clamp(0, in_len as i64 - 1);
+    let right = ((center + support).ceil() as i64).clamp(left + 1, in_len as i64);
+    // the kernel treats the center of a pixel as 0
+    let center = center - 0.5;
+
+    weights.clear();
+    let mut sum = 0.0;
+    for i in left..right {
+        let x = ((i as f32 - center) / sratio).abs();
+        let w = if x < 1.0 { 1.0 - x } else { 0.0 };
+        weights.push(w);
+        sum += w;
+    }
+    weights.iter_mut().for_each(|w| *w /= sum);
+    left as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::imageops::FilterType;
+
+    #[test]
+    fn test_resize_same_as_image() {
+        let img = image::open("./assets/test.jpg").unwrap();
+        let (w, h) = fit_dimensions(img.width(), img.height(), 128, 128);
+        let expect = img.resize(128, 128, FilterType::Triangle).to_rgb8();
+        assert_eq!(expect.dimensions(), (w, h));
+
+        let mut resizer = ImageResizer::new();
+        let mut dst = RgbImage::new(w, h);
+        resizer.resize(&img.to_rgb8(), &mut dst);
+        for (a, b) in expect.as_raw().iter().zip(dst.as_raw()) {
+            assert!((*a as i32 - *b as i32).abs() <= 1);
+        }
+
+        // upscaling
+        let small = RgbImage::from_fn(3, 2, |x, y| {
+            image::Rgb([(x * 80) as u8, (y * 200) as u8, 7])
+        });
+        let expect = image::imageops::resize(&small, 7, 5, FilterType::Triangle);
+        let mut dst = RgbImage::new(7, 5);
+        resizer.resize(&small, &mut dst);
+        for (a, b) in expect.as_raw().iter().zip(dst.as_raw()) {
+            assert!((*a as i32 - *b as i32).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_resize_empty_image() {
+        let mut resizer = ImageResizer::new();
+        let mut dst = RgbImage::from_pixel(4, 4, image::Rgb([200, 100, 50]));
+        resizer.resize(&RgbImage::new(0, 3), &mut dst);
+        assert!(dst.as_raw().iter().all(|p| *p == 0));
+    }
+}
@@ -0,0 +1,278 @@
+use super::{ToTensor, ToTensorRef};
+use crate::inference::{
+    InferenceTensor, InferenceTensorDataLayout, InferenceTensorQuantization, InferenceTensorType,
+};
+
+/// how the pixel values (0 ~ 255) are mapped to the tensor values
+#[derive(Debug, Clone, PartialEq)]
+pub enum Normalization {
+    /// map to [-1, 1]: ```p * 2 / 255 - 1```, it is used by the mediapipe models
+    MinusOneToOne,
+    /// map to [0, 1]: ```p / 255```
+    ZeroToOne,
+    /// per-channel ```(p / 255 - mean) / std```, the channels are in the output channel order
+    MeanStd { mean: [f32; 3], std: [f32; 3] },
+    /// the raw pixel values, such as the uint8 input of the quantized models
+    Raw,
+}
+
+impl Normalization {
+    /// the mean and std of ImageNet (RGB order), used by most torchvision models
+    pub const IMAGENET: Normalization = Normalization::MeanStd {
+        mean: [0.485, 0.456, 0.406],
+        std: [0.229, 0.224, 0.225],
+    };
+
+    /// map the pixel value of the output ```channel```
+    #[inline]
+    pub fn apply(&self, p: u8, channel: usize) -> f32 {
+        match self {
+            Normalization::MinusOneToOne => {
+                const MULTIPLY: f32 = 2.0f32 / 255.0f32;
+                (p as f32) * MULTIPLY - 1.0f32
+            }
+            Normalization::ZeroToOne => p as f32 / 255.0,
+            Normalization::MeanStd { mean, std } => {
+                (p as f32 / 255.0 - mean[channel]) / std[channel]
+            }
+            Normalization::Raw => p as f32,
+        }
+    }
+
+    /// the mapped values of all pixel values for each output channel
+    pub fn lookup_table(&self) -> [[f32; 256]; 3] {
+        let mut table = [[0f32; 256]; 3];
+        for (channel, values) in table.iter_mut().enumerate() {
+            for (p, v) in values.iter_mut().enumerate() {
+                *v = self.apply(p as u8, channel);
+            }
+        }
+        table
+    }
+}
+
+/// the order of the channels in the tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOrder {
+    Rgb,
+    /// such as the OpenCV and Caffe models
+    Bgr,
+}
+
+impl ChannelOrder {
+    /// the source (RGB) channel of each output channel
+    #[inline(always)]
+    pub fn source_channels(&self) -> [usize; 3] {
+        match self {
+            ChannelOrder::Rgb => [0, 1, 2],
+            ChannelOrder::Bgr => [2, 1, 0],
+        }
+    }
+}
+
+/// Tensor Conversion Options
+/// how an image is converted to a tensor: the data layout, the tensor type (and quantization),
+/// the value normalization and the channel order.
+///
+/// the default is the mediapipe convention: NHWC, f32, [-1, 1] and RGB.
+///
+/// ### Examples
+///
+/// ```
+/// use mediapipe_wasinn_demo::inference::InferenceTensorDataLayout;
+/// use mediapipe_wasinn_demo::preprocess::{ChannelOrder, Normalization, TensorConversionOptions, ToTensor};
+///
+/// let img = image::RgbImage::new(224, 224);
+/// // a torchvision model
+/// let options = TensorConversionOptions::default()
+///     .layout(InferenceTensorDataLayout::NCHW)
+///     .normalization(Normalization::IMAGENET);
+/// let tensor = img.to_tensor_with_options(&options);
+/// assert_eq!(tensor.shape_ref(), &[1, 3, 224, 224]);
+///
+/// // an OpenCV model with raw uint8 BGR input
+/// let options = TensorConversionOptions::raw_u8(InferenceTensorDataLayout::NHWC)
+///     .channel_order(ChannelOrder::Bgr);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorConversionOptions {
+    pub layout: InferenceTensorDataLayout,
+    pub tp: InferenceTensorType,
+    /// only used for integer types, ```None``` is scale 1 and zero point 0
+    pub quantization: Option<InferenceTensorQuantization>,
+    pub normalization: Normalization,
+    pub channel_order: ChannelOrder,
+}
+
+impl Default for TensorConversionOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::mediapipe(InferenceTensorDataLayout::NHWC)
+    }
+}
+
+impl TensorConversionOptions {
+    /// f32 values in [-1, 1] and RGB order, used by the mediapipe models
+    #[inline]
+    pub fn mediapipe(layout: InferenceTensorDataLayout) -> Self {
+        Self {
+            layout,
+            tp: InferenceTensorType::F32,
+            quantization: None,
+            normalization: Normalization::MinusOneToOne,
+            channel_order: ChannelOrder::Rgb,
+        }
+    }
+
+    /// the raw uint8 pixel values in RGB order
+    #[inline]
+    pub fn raw_u8(layout: InferenceTensorDataLayout) -> Self {
+        Self {
+            layout,
+            tp: InferenceTensorType::U8,
+            quantization: None,
+            normalization: Normalization::Raw,
+            channel_order: ChannelOrder::Rgb,
+        }
+    }
+
+    #[inline]
+    pub fn layout(mut self, layout: InferenceTensorDataLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// set the tensor type, the normalized values are quantized with ```quantization```
+    /// if ```tp``` is an integer type.
+    #[inline]
+    pub fn tensor_type(
+        mut self,
+        tp: InferenceTensorType,
+        quantization: Option<InferenceTensorQuantization>,
+    ) -> Self {
+        self.tp = tp;
+        self.quantization = quantization;
+        self
+    }
+
+    #[inline]
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    #[inline]
+    pub fn channel_order(mut self, channel_order: ChannelOrder) -> Self {
+        self.channel_order = channel_order;
+        self
+    }
+
+    /// whether the tensor values are same as the raw uint8 pixels (in RGB order)
+    pub fn is_raw_u8(&self) -> bool {
+        self.tp == InferenceTensorType::U8
+            && self.normalization == Normalization::Raw
+            && self.channel_order == ChannelOrder::Rgb
+            && self
+                .quantization
+                .as_ref()
+                .filter(|q| q.scale != 1.0 || q.zero_point != 0)
+                .is_none()
+    }
+}
+
+/// Tensor Scratch
+/// a reusable buffer to convert images to tensors, the tensor refers to the buffer,
+/// so converting the frames with the same size does not allocate memory.
+///
+/// if the options are the raw uint8 values and the image can be used directly
+/// (see [`ToTensorRef`]), the tensor refers to the image and nothing is copied.
+///
+/// ### Examples
+///
+/// ```no_run
+/// use mediapipe_wasinn_demo::inference::InferenceGraphBuilder;
+/// use mediapipe_wasinn_demo::preprocess::{TensorConversionOptions, TensorScratch};
+///
+/// let graph = InferenceGraphBuilder::default().build_from_file("./module.tflite")?;
+/// let mut executor = graph.new_graph_executor()?;
+/// let options = TensorConversionOptions::default();
+/// let frames = vec![image::RgbImage::new(128, 128); 3];
+///
+/// let mut scratch = TensorScratch::new();
+/// for frame in frames.iter() {
+///     let input = scratch.tensor(frame, &options);
+///     executor.set_inputs_and_run([(0, input)])?;
+/// }
+/// # Ok::<(), mediapipe_wasinn_demo::inference::InferenceError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct TensorScratch {
+    shape: Vec<u32>,
+    data: Vec<u8>,
+}
+
+impl TensorScratch {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// convert ```src``` with ```options```, the tensor refers to ```src``` or this scratch.
+    pub fn tensor<'a, T: ToTensor + ToTensorRef + ?Sized>(
+        &'a mut self,
+        src: &'a T,
+        options: &TensorConversionOptions,
+    ) -> InferenceTensor<'a> {
+        if options.is_raw_u8() {
+            if let Some(data) = src.tensor_ref_data(&options.layout, &mut self.shape) {
+                return InferenceTensor::new_ref(
+                    InferenceTensorType::U8,
+                    options.layout.clone(),
+                    &self.shape,
+                    data,
+                );
+            }
+        }
+        src.to_tensor_with_options_into(options, &mut self.shape, &mut self.data);
+        InferenceTensor::new_ref(
+            options.tp.clone(),
+            options.layout.clone(),
+            &self.shape,
+            &self.data,
+        )
+    }
+
+    /// the capacity of the buffer in bytes
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(Normalization::MinusOneToOne.apply(0, 0), -1.0);
+        assert_eq!(Normalization::MinusOneToOne.apply(255, 2), 1.0);
+        assert_eq!(Normalization::ZeroToOne.apply(51, 1), 0.2);
+        assert_eq!(Normalization::Raw.apply(7, 0), 7.0);
+
+        let mean_std = Normalization::MeanStd {
+            mean: [0.0, 0.5, 1.0],
+            std: [1.0, 0.5, 0.25],
+        };
+        assert_eq!(mean_std.apply(255, 0), 1.0);
+        assert_eq!(mean_std.apply(255, 1), 1.0);
+        assert_eq!(mean_std.apply(0, 2), -4.0);
+
+        let table = mean_std.lookup_table();
+        for (channel, values) in table.iter().enumerate() {
+            for (p, v) in values.iter().enumerate() {
+                assert_eq!(*v, mean_std.apply(p as u8, channel));
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Profiler
+/// record the durations of the stages (such as ```resize```, ```to_tensor```, ```set_input```,
+/// ```compute```, ```get_output``` and ```decode```) of every call.
+///
+/// profiling is opt-in, the default profiler is disabled and records nothing.
+/// the profiler is a shared handle, clone is cheap and all clones record to the same place,
+/// so it can be used by several threads.
+///
+/// ### Examples
+///
+/// ```no_run
+/// use mediapipe_wasinn_demo::{FaceDetection, FaceDetectionModels, Profiler};
+///
+/// let profiler = Profiler::new();
+/// let face_detection = FaceDetection::new(FaceDetectionModels::ShortRange, 0.5)?
+///     .profiler(profiler.clone());
+/// let images = [image::open("./assets/test.jpg")?];
+/// for img in images.iter() {
+///     face_detection.process(img)?;
+/// }
+/// println!("{}", profiler.report());
+/// profiler.write_chrome_trace("./trace.json")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct Profiler {
+    inner: Option<Arc<ProfilerInner>>,
+}
+
+struct ProfilerInner {
+    /// the time origin of records
+    origin: Instant,
+    records: Mutex<Vec<ProfileRecord>>,
+}
+
+/// the duration of one stage in one call
+#[derive(Debug, Clone)]
+pub struct ProfileRecord {
+    pub stage: &'static str,
+    /// the start time since the profiler is created
+    pub start: Duration,
+    pub duration: Duration,
+    /// the index of the thread which records it, the first thread is 0
+    pub thread: u32,
+}
+
+/// the statistics of one stage across all calls
+#[derive(Debug, Clone)]
+pub struct ProfileStageStats {
+    pub stage: &'static str,
+    pub count: usize,
+    pub total: Duration,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl Profiler {
+    /// create an enabled profiler
+    pub fn new() -> Self {
+        Self {
+            inner: Some(Arc::new(ProfilerInner {
+                origin: Instant::now(),
+                records: Mutex::new(Vec::new()),
+            })),
+        }
+    }
+
+    /// create a disabled profiler, same as ```Profiler::default()```
+    #[inline(always)]
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// start a stage, the duration is recorded when the returned scope is dropped.
+    /// nothing is done if the profiler is disabled.
+    #[inline]
+    pub fn scope(&self, stage: &'static str) -> ProfileScope {
+        ProfileScope {
+            inner: self.inner.clone(),
+            stage,
+            start: self.inner.as_ref().map(|_| Instant::now()),
+        }
+    }
+
+    /// record a stage which starts at ```start``` and ends now
+    pub fn record(&self, stage: &'static str, start: Instant) {
+        if let Some(inner) = self.inner.as_ref() {
+            inner.record(stage, start, Instant::now());
+        }
+    }
+
+    /// all records in the order of end time
+    pub fn records(&self) -> Vec<ProfileRecord> {
+        match self.inner.as_ref() {
+            Some(inner) => inner.records.lock().unwrap().clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// remove all records
+    pub fn clear(&self) {
+        if let Some(inner) = self.inner.as_ref() {
+            inner.records.lock().unwrap().clear();
+        }
+    }
+
+    /// the statistics of each stage, in the order of the first record of the stage
+    pub fn stats(&self) -> Vec<ProfileStageStats> {
+        let mut stages: Vec<(&'static str, Vec<Duration>)> = Vec::new();
+        for r in self.records() {
+            match stages.iter_mut().find(|(stage, _)| *stage == r.stage) {
+                Some((_, durations)) => durations.push(r.duration),
+                None => stages.push((r.stage, vec![r.duration])),
+            }
+        }
+
+        stages
+            .into_iter()
+            .map(|(stage, mut durations)| {
+                durations.sort_unstable();
+                let count = durations.len();
+                let total = durations.iter().sum::<Duration>();
+                // nearest rank
+                let p95_rank = (count * 95).div_ceil(100);
+                ProfileStageStats {
+                    stage,
+                    count,
+                    total,
+                    min: durations[0],
+                    mean: total / count as u32,
+                    p95: durations[p95_rank.max(1) - 1],
+                    max: durations[count - 1],
+                }
+            })
+            .collect()
+    }
+
+    /// the statistics as a text table, the durations are in milliseconds
+    pub fn report(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mut s = format!(
+            "{:<16}{:>8}{:>12}{:>10}{:>10}{:>10}{:>10}\n",
+            "stage", "count", "total(ms)", "min", "mean", "p95", "max"
+        );
+        for st in self.stats() {
+            let _ = writeln!(
+                s,
+                "{:<16}{:>8}{:>12.3}{:>10.3}{:>10.3}{:>10.3}{:>10.3}",
+                st.stage,
+                st.count,
+                ms(st.total),
+                ms(st.min),
+                ms(st.mean),
+                ms(st.p95),
+                ms(st.max)
+            );
+        }
+        s
+    }
+
+    /// the records as Chrome trace-event JSON, which can be opened by ```chrome://tracing```
+    /// or [Perfetto](https://ui.perfetto.dev)
+    pub fn chrome_trace(&self) -> String {
+        let us = |d: Duration| d.as_nanos() as f64 / 1000.0;
+        let events = self
+            .records()
+            .iter()
+            .map(|r| {
+                format!(
+                    r#"{{"name":"{}","cat":"mediapipe","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":{}}}"#,
+                    r.stage,
+                    us(r.start),
+                    us(r.duration),
+                    r.thread
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"traceEvents\":[{}],\"displayTimeUnit\":\"ms\"}}",
+            events.join(",")
+        )
+    }
+
+    /// write the Chrome trace-event JSON to the file
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.chrome_trace())
+    }
+}
+
+impl ProfilerInner {
+    fn record(&self, stage: &'static str, start: Instant, end: Instant) {
+        let record = ProfileRecord {
+            stage,
+            start: start.saturating_duration_since(self.origin),
+            duration: end.saturating_duration_since(start),
+            thread: thread_index(),
+        };
+        self.records.lock().unwrap().push(record);
+    }
+}
+
+/// a running stage of the [`Profiler`], it is recorded when dropped
+pub struct ProfileScope {
+    inner: Option<Arc<ProfilerInner>>,
+    stage: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if let (Some(inner), Some(start)) = (self.inner.as_ref(), self.start) {
+            inner.record(self.stage, start, Instant::now());
+        }
+    }
+}
+
+/// the index of current thread, it is used as the ```tid``` of trace events
+fn thread_index() -> u32 {
+    static NEXT_THREAD_INDEX: AtomicU32 = AtomicU32::new(0);
+    thread_local! {
+        static THREAD_INDEX: u32 = NEXT_THREAD_INDEX.fetch_add(1, Ordering::Relaxed);
+    }
+    THREAD_INDEX.with(|i| *i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_profiler_stats_and_trace() {
+        let disabled = Profiler::default();
+        drop(disabled.scope("compute"));
+        assert!(!disabled.is_enabled());
+        assert!(disabled.records().is_empty());
+
+        let profiler = Profiler::new();
+        for i in 1..=20u64 {
+            let start = Instant::now() - Duration::from_millis(i);
+            profiler.record("compute", start);
+        }
+        drop(profiler.clone().scope("decode"));
+
+        let stats = profiler.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].stage, stats[0].count), ("compute", 20));
+        assert_eq!((stats[1].stage, stats[1].count), ("decode", 1));
+        let ms = |d: Duration| d.as_millis();
+        assert_eq!(ms(stats[0].min), 1);
+        assert_eq!(ms(stats[0].p95), 19);
+        assert_eq!(ms(stats[0].max), 20);
+        assert!(stats[0].min <= stats[0].mean && stats[0].mean <= stats[0].max);
+
+        let report = profiler.report();
+        assert_eq!(report.lines().count(), 3);
+        assert!(report.lines().nth(1).unwrap().starts_with("compute"));
+
+        let trace = profiler.chrome_trace();
+        assert!(trace.starts_with("{\"traceEvents\":[{\"name\":\"compute\""));
+        assert_eq!(trace.matches("\"ph\":\"X\"").count(), 21);
+
+        profiler.clear();
+        assert!(profiler.stats().is_empty());
+    }
+}
@@ -1,109 +1,490 @@
+use std::borrow::Cow;
 use std::cmp::{min, Ordering};
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 
-use image::DynamicImage;
+use image::{DynamicImage, RgbImage};
 
+use crate::error::{Error, Result};
 use crate::inference::*;
+use crate::model_locator::{ModelLocator, ModelSource};
 use crate::postprocess::{ops::Sigmoid, Anchor, Box2D, Pointer2D, SsdAnchorsGeneratorOptions};
-use crate::preprocess::ToTensor;
+use crate::preprocess::{ImageToTensor, LetterboxPadding, ToTensor};
+use crate::profiler::Profiler;
+use crate::solutions::{ModelIoSpec, TensorSpec};
 
+/// the face detection solution, clone is cheap and the clones share the graph and anchors.
+#[derive(Clone)]
 pub struct FaceDetection {
     graph: InferenceGraph,
-    anchors: Vec<Anchor>,
+    /// the executors used by ```process```
+    executor_pool: InferenceGraphExecutorPool,
+    anchors: Arc<Vec<Anchor>>,
     min_detection_confidence: f32,
+    profiler: Profiler,
 }
 
 /// ref: https://github.com/google/mediapipe/blob/master/mediapipe/modules/face_detection/face_detection.pbtxt
 /// https://github.com/google/mediapipe/blob/master/mediapipe/examples/desktop/autoflip/subgraph/front_face_detection_subgraph.pbtxt
 impl FaceDetection {
+    /// the default max number of concurrent ```process``` calls
+    pub const DEFAULT_EXECUTOR_POOL_SIZE: usize = 4;
+
+    /// the inputs and outputs of the short range model
+    pub const MODEL_IO_SPEC: ModelIoSpec = ModelIoSpec {
+        solution: "face detection",
+        inputs: &[TensorSpec {
+            name: "image",
+            shape: &[1, 128, 128, 3],
+        }],
+        outputs: &[
+            TensorSpec {
+                name: "regressors",
+                shape: &[1, 896, 16],
+            },
+            TensorSpec {
+                name: "classificators",
+                shape: &[1, 896, 1],
+            },
+        ],
+    };
+
     pub fn new(
         module_selection: FaceDetectionModels,
         min_detection_confidence: f32,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let graph =
-            InferenceGraphBuilder::default().build_from_file(module_selection.to_model_path())?;
+    ) -> Result<Self> {
+        Self::new_with_builder(
+            module_selection,
+            min_detection_confidence,
+            InferenceGraphBuilder::default(),
+        )
+    }
+
+    /// create face detection with a custom graph builder (such as use another inference backend).
+    ///
+    /// the model is found in order:
+    /// 1. the directories in environment variable [`MODEL_DIR_ENV`](crate::MODEL_DIR_ENV),
+    ///    so a model file can override the embedded model
+    /// 2. the model embedded by cargo feature (it is not copied)
+    /// 3. the search paths of the default [`ModelLocator`]
+    pub fn new_with_builder(
+        module_selection: FaceDetectionModels,
+        min_detection_confidence: f32,
+        graph_builder: InferenceGraphBuilder,
+    ) -> Result<Self> {
+        let locator = ModelLocator::default();
+        if let Some(model) = module_selection.embedded_model() {
+            let name = module_selection.model_file_name()?;
+            let overridden = locator
+                .env_dirs()
+                .iter()
+                .any(|dir| dir.join(name).is_file());
+            if !overridden {
+                let graph = graph_builder
+                    .build_from_static_bytes(model)
+                    .map_err(|e| Error::model_load(name, e))?;
+                return Self::new_with_graph(graph, min_detection_confidence);
+            }
+        }
+        Self::new_with_locator(
+            module_selection,
+            min_detection_confidence,
+            &locator,
+            graph_builder,
+        )
+    }
+
+    /// create face detection, the model file is found by the ```locator```
+    pub fn new_with_locator(
+        module_selection: FaceDetectionModels,
+        min_detection_confidence: f32,
+        locator: &ModelLocator,
+        graph_builder: InferenceGraphBuilder,
+    ) -> Result<Self> {
+        let source = ModelSource::Name(module_selection.model_file_name()?.to_string());
+        let graph = locator.build_graph(&source, graph_builder)?;
+        Self::new_with_graph(graph, min_detection_confidence)
+    }
+
+    /// create face detection with the model from an explicit path or memory,
+    /// the model must be a short range model
+    pub fn new_with_source(
+        source: ModelSource,
+        min_detection_confidence: f32,
+        graph_builder: InferenceGraphBuilder,
+    ) -> Result<Self> {
+        let graph = ModelLocator::new().build_graph(&source, graph_builder)?;
+        Self::new_with_graph(graph, min_detection_confidence)
+    }
+
+    /// create face detection with a loaded graph, the graph must be a short range model.
+    /// the graph is checked by [`FaceDetection::MODEL_IO_SPEC`].
+    pub fn new_with_graph(graph: InferenceGraph, min_detection_confidence: f32) -> Result<Self> {
+        Self::MODEL_IO_SPEC.validate(&graph)?;
         let mut anchor_generator_opt =
             SsdAnchorsGeneratorOptions::new(128, 128, 0.1484375, 0.75, 4);
         anchor_generator_opt.aspect_ratios.push(1.0f32);
         anchor_generator_opt.fixed_anchor_size = true;
         anchor_generator_opt.strides = vec![8, 16, 16, 16];
         Ok(Self {
+            executor_pool: graph.new_executor_pool(Self::DEFAULT_EXECUTOR_POOL_SIZE),
             graph,
-            anchors: anchor_generator_opt.generate(),
+            anchors: Arc::new(anchor_generator_opt.generate()),
             min_detection_confidence,
+            profiler: Profiler::default(),
         })
     }
 
-    // just process once
-    pub fn process(
+    /// set the max number of concurrent ```process``` calls, the other calls wait for a free executor.
+    /// the clones of this face detection share the pool created here, 0 is same as 1.
+    pub fn executor_pool_size(mut self, size: usize) -> Self {
+        self.executor_pool = self.graph.new_executor_pool(size);
+        self
+    }
+
+    /// record the durations of ```process```, ```resize```, ```to_tensor```, ```decode```
+    /// and the backend calls, see [`Profiler`].
+    /// the processors generated after this call use the profiler too.
+    pub fn profiler(mut self, profiler: Profiler) -> Self {
+        self.profiler = profiler;
+        self
+    }
+
+    /// run the model ```n``` times with zero inputs in every executor of the pool,
+    /// so the slow first inference is done before the real images.
+    /// the warmed up executors are kept in the pool for ```process```, the executors used by
+    /// concurrent ```process``` calls are skipped, see [`InferenceGraphExecutorPool::warmup`].
+    pub fn warmup(&self, n: usize) -> Result<()> {
+        Ok(self.executor_pool.warmup(n)?)
+    }
+
+    /// whether all executors used by ```process``` are warmed up
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.executor_pool.is_ready()
+    }
+
+    /// process once, the executor is got from the pool and can be used concurrently.
+    /// use ```generate_processor``` for a stream to hold an executor.
+    pub fn process(&self, image: &DynamicImage) -> Result<Vec<FaceDetectionModelOutput>> {
+        let mut executor = self.executor_pool.acquire()?;
+        executor.set_profiler(self.profiler.clone());
+        self.process_with_executor(&mut executor, image)
+    }
+
+    /// process several images, return the results of each image.
+    /// the images are stacked to one batch and run once if the model has a dynamic batch,
+    /// otherwise (the model has a fixed batch of 1) the images are run one by one.
+    pub fn process_batch(
         &self,
-        image: &DynamicImage,
-    ) -> Result<Vec<FaceDetectionModelOutput>, Box<dyn std::error::Error>> {
-        self.generate_processor()?.process_img(image)
+        images: &[DynamicImage],
+    ) -> Result<Vec<Vec<FaceDetectionModelOutput>>> {
+        let mut executor = self.executor_pool.acquire()?;
+        executor.set_profiler(self.profiler.clone());
+        self.process_batch_with_executor(&mut executor, images)
     }
 
-    pub fn generate_processor(&self) -> Result<FaceDetectionProcessor, Box<dyn std::error::Error>> {
-        let graph_exec = self.graph.new_graph_executor()?;
+    pub fn generate_processor(&self) -> Result<FaceDetectionProcessor> {
+        let mut graph_exec = self.graph.new_graph_executor()?;
+        graph_exec.set_profiler(self.profiler.clone());
         Ok(FaceDetectionProcessor {
-            face_detection: self,
+            frame: FrameBuffers::new(&self.graph),
+            face_detection: self.clone(),
             graph_exec,
         })
     }
 }
 
 /// process a image or stream
-pub struct FaceDetectionProcessor<'a> {
-    face_detection: &'a FaceDetection,
-    graph_exec: InferenceGraphExecutor<'a>,
+/// the processor owns its executor, so it can be stored or moved to another thread.
+pub struct FaceDetectionProcessor {
+    face_detection: FaceDetection,
+    graph_exec: InferenceGraphExecutor,
+    frame: FrameBuffers,
 }
 
-impl<'a> FaceDetectionProcessor<'a> {
-    const REGRESSORS_SIZE: u32 = 1 * 896 * 16;
-    const SCORES_SIZE: u32 = 1 * 896 * 1;
+/// the reusable buffers for ```process_frame```
+struct FrameBuffers {
+    image_to_tensor: ImageToTensor,
+    shape: Vec<u32>,
+    input: Vec<u8>,
+    regressors: Vec<f32>,
+    scores: Vec<f32>,
+    results: Vec<FaceDetectionModelOutput>,
+}
+
+impl FrameBuffers {
+    /// the buffers are sized from the graph inputs and outputs information
+    fn new(graph: &InferenceGraph) -> Self {
+        let input_len = graph.inputs().first().map_or(0, |i| i.byte_len());
+        let output_len =
+            |index: usize| graph.outputs().get(index).map_or(0, |o| o.elements_count());
+        Self {
+            image_to_tensor: FaceDetection::image_to_tensor(),
+            shape: Vec::new(),
+            input: Vec::with_capacity(input_len),
+            regressors: Vec::with_capacity(output_len(0)),
+            scores: Vec::with_capacity(output_len(1)),
+            results: Vec::new(),
+        }
+    }
+}
+
+impl FaceDetectionProcessor {
+    /// warm up the executor of this processor, see [`FaceDetection::warmup`]
+    #[inline]
+    pub fn warmup(&mut self, n: usize) -> Result<()> {
+        Ok(self.graph_exec.warmup(n)?)
+    }
+
+    /// whether the executor of this processor is warmed up
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.graph_exec.is_ready()
+    }
+
+    #[inline]
+    pub fn process_img(&mut self, image: &DynamicImage) -> Result<Vec<FaceDetectionModelOutput>> {
+        self.face_detection
+            .process_with_executor(&mut self.graph_exec, image)
+    }
+
+    /// process a frame of a video, the results are valid until the next frame.
+    /// the processor owns the buffers of the resized image, input tensor and outputs,
+    /// so after the first frame no memory is allocated for the frames with the same size.
+    pub fn process_frame(&mut self, frame: &RgbImage) -> Result<&[FaceDetectionModelOutput]> {
+        let face_detection = &self.face_detection;
+        let profiler = &face_detection.profiler;
+        let buffers = &mut self.frame;
+        let _scope = profiler.scope("process");
+
+        // letterbox as ```process_img```
+        let scope = profiler.scope("resize");
+        let (resized, padding) = buffers.image_to_tensor.convert(frame);
+        drop(scope);
+
+        let scope = profiler.scope("to_tensor");
+        let (tp, quantization) = face_detection.input_encoding();
+        resized.to_tensor_data_into(
+            &InferenceTensorDataLayout::NHWC,
+            &tp,
+            quantization,
+            &mut buffers.shape,
+            &mut buffers.input,
+        );
+        drop(scope);
+        let input = InferenceTensor::new_ref(
+            tp,
+            InferenceTensorDataLayout::NHWC,
+            &buffers.shape,
+            &buffers.input,
+        );
+        self.graph_exec.set_inputs_and_run([(0, input)])?;
 
-    pub fn process_img(
+        self.graph_exec
+            .get_output_dequantized_into(0, &mut buffers.regressors)?;
+        self.graph_exec
+            .get_output_dequantized_into(1, &mut buffers.scores)?;
+        let _scope = profiler.scope("decode");
+        buffers.results.clear();
+        FaceDetectionModelOutput::extend_with_threshold(
+            &face_detection.anchors,
+            &buffers.regressors,
+            &mut buffers.scores,
+            face_detection.min_detection_confidence,
+            &mut buffers.results,
+        );
+        buffers
+            .results
+            .iter_mut()
+            .for_each(|r| r.remove_letterbox(&padding));
+        Ok(&buffers.results)
+    }
+
+    /// process several images, see [`FaceDetection::process_batch`]
+    #[inline]
+    pub fn process_imgs(
         &mut self,
+        images: &[DynamicImage],
+    ) -> Result<Vec<Vec<FaceDetectionModelOutput>>> {
+        self.face_detection
+            .process_batch_with_executor(&mut self.graph_exec, images)
+    }
+}
+
+impl FaceDetection {
+    fn process_with_executor(
+        &self,
+        graph_exec: &mut InferenceGraphExecutor,
         image: &DynamicImage,
-    ) -> Result<Vec<FaceDetectionModelOutput>, Box<dyn std::error::Error>> {
-        // generate input
-        let input = image
-            .resize(128, 128, image::imageops::FilterType::Triangle)
-            .to_rgb8()
-            .to_tensor(InferenceTensorDataLayout::NHWC);
+    ) -> Result<Vec<FaceDetectionModelOutput>> {
+        let _scope = self.profiler.scope("process");
+        let (input, padding) = self.input_tensor(&mut Self::image_to_tensor(), image);
 
         // do inference
-        self.graph_exec.set_inputs_and_run([(0, input)])?;
+        graph_exec.set_inputs_and_run([(0, input)])?;
 
         // get output
-        let regressors = self.graph_exec.get_output_f32(0, Self::REGRESSORS_SIZE)?;
-        let mut scores = self.graph_exec.get_output_f32(1, Self::SCORES_SIZE)?;
+        // quantized and fp16 outputs are converted to f32
+        let regressors = graph_exec.get_output_dequantized(0)?;
+        let mut scores = graph_exec.get_output_dequantized(1)?;
 
-        Ok(FaceDetectionModelOutput::from_with_threshold(
-            &self.face_detection.anchors,
+        let _scope = self.profiler.scope("decode");
+        let mut results = FaceDetectionModelOutput::from_with_threshold(
+            &self.anchors,
             &regressors,
             &mut scores,
-            self.face_detection.min_detection_confidence,
-        ))
+            self.min_detection_confidence,
+        );
+        results
+            .iter_mut()
+            .for_each(|r| r.remove_letterbox(&padding));
+        Ok(results)
+    }
+
+    fn process_batch_with_executor(
+        &self,
+        graph_exec: &mut InferenceGraphExecutor,
+        images: &[DynamicImage],
+    ) -> Result<Vec<Vec<FaceDetectionModelOutput>>> {
+        let dynamic_batch = matches!(self.graph.inputs().first(), Some(info) if info.dynamic_batch);
+        if !dynamic_batch || images.len() <= 1 {
+            return images
+                .iter()
+                .map(|img| self.process_with_executor(graph_exec, img))
+                .collect();
+        }
+
+        let _scope = self.profiler.scope("process");
+        let mut image_to_tensor = Self::image_to_tensor();
+        let (inputs, paddings): (Vec<_>, Vec<_>) = images
+            .iter()
+            .map(|img| self.input_tensor(&mut image_to_tensor, img))
+            .unzip();
+        let input = InferenceTensor::stack(&inputs)?;
+        graph_exec.set_inputs_and_run([(0, input)])?;
+
+        // split the outputs to each image
+        let batch_size = images.len() as u32;
+        let regressors = graph_exec.get_output_dequantized_batch(0, batch_size)?;
+        let scores = graph_exec.get_output_dequantized_batch(1, batch_size)?;
+        let anchors_num = self.anchors.len();
+        let _scope = self.profiler.scope("decode");
+        Ok(regressors
+            .chunks_exact(anchors_num << 4)
+            .zip(scores.chunks_exact(anchors_num))
+            .zip(paddings)
+            .map(|((regressors, scores), padding)| {
+                let mut results = FaceDetectionModelOutput::from_with_threshold(
+                    &self.anchors,
+                    regressors,
+                    &mut scores.to_vec(),
+                    self.min_detection_confidence,
+                );
+                results
+                    .iter_mut()
+                    .for_each(|r| r.remove_letterbox(&padding));
+                results
+            })
+            .collect())
+    }
+
+    /// the input type of the model, quantized model uses the quantization parameters
+    /// of model input, and fp16 model uses the f16 input
+    fn input_encoding(&self) -> (InferenceTensorType, Option<&InferenceTensorQuantization>) {
+        match self.graph.inputs().first() {
+            Some(InferenceTensorInfo {
+                tp: tp @ (InferenceTensorType::U8 | InferenceTensorType::I8),
+                quantization: Some(quantization),
+                ..
+            }) => (tp.clone(), Some(quantization)),
+            Some(InferenceTensorInfo {
+                tp: InferenceTensorType::F16,
+                ..
+            }) => (InferenceTensorType::F16, None),
+            _ => (InferenceTensorType::F32, None),
+        }
+    }
+
+    /// the converter to the model input size, the input shape is ```[1, height, width, 3]```
+    fn image_to_tensor() -> ImageToTensor {
+        let shape = Self::MODEL_IO_SPEC.inputs[0].shape;
+        ImageToTensor::new(shape[2], shape[1])
+    }
+
+    /// the input tensor of the letterboxed image and the paddings,
+    /// the rgb8 images are not copied before resizing
+    fn input_tensor(
+        &self,
+        image_to_tensor: &mut ImageToTensor,
+        image: &DynamicImage,
+    ) -> (InferenceTensor<'static>, LetterboxPadding) {
+        let scope = self.profiler.scope("resize");
+        let rgb = match image.as_rgb8() {
+            Some(rgb) => Cow::Borrowed(rgb),
+            None => Cow::Owned(image.to_rgb8()),
+        };
+        let (image, padding) = image_to_tensor.convert(&rgb);
+        drop(scope);
+
+        let _scope = self.profiler.scope("to_tensor");
+        let (tp, quantization) = self.input_encoding();
+        let mut shape = Vec::new();
+        let mut data = Vec::new();
+        image.to_tensor_data_into(
+            &InferenceTensorDataLayout::NHWC,
+            &tp,
+            quantization,
+            &mut shape,
+            &mut data,
+        );
+        let tensor = InferenceTensor::new(tp, InferenceTensorDataLayout::NHWC, shape, data);
+        (tensor, padding)
     }
 }
 
+/// the face detection models, other quantized models (uint8 or int8) can be loaded
+/// with [`FaceDetection::new_with_graph`].
 #[derive(Debug, Clone)]
 pub enum FaceDetectionModels {
     ShortRange,
+    /// the uint8 quantized short range model, it is not in the assets,
+    /// so put it in a search path of the [`ModelLocator`]
     ShortRangeQuantized,
     FullRangeDense,
     FullRangeSparse,
 }
 
 impl FaceDetectionModels {
-    fn to_model_path(self) -> &'static str {
+    /// the model content embedded into the binary, it is enabled by the cargo feature
+    /// ```embed-<model name>``` (such as ```embed-face-detection-short-range```)
+    pub fn embedded_model(&self) -> Option<&'static [u8]> {
+        match self {
+            #[cfg(feature = "embed-face-detection-short-range")]
+            FaceDetectionModels::ShortRange => Some(include_bytes!(
+                "../../assets/face_detection_short_range.tflite"
+            )),
+            _ => None,
+        }
+    }
+
+    /// the model file name which is searched by [`ModelLocator`],
+    /// ```None``` if the model is not supported now
+    pub fn file_name(&self) -> Option<&'static str> {
         match self {
-            FaceDetectionModels::ShortRange => "./assets/face_detection_short_range.tflite",
-            _ => {
-                unimplemented!()
+            FaceDetectionModels::ShortRange => Some("face_detection_short_range.tflite"),
+            FaceDetectionModels::ShortRangeQuantized => {
+                Some("face_detection_short_range_quantized.tflite")
             }
+            FaceDetectionModels::FullRangeDense | FaceDetectionModels::FullRangeSparse => None,
         }
     }
+
+    /// the model file name, or ```Error::Config``` if the model is not supported
+    fn model_file_name(&self) -> Result<&'static str> {
+        self.file_name()
+            .ok_or_else(|| Error::Config(format!("{:?} model is not supported", self)))
+    }
 }
 
 /// Module Info: https://mediapipe.page.link/blazeface-mc
@@ -228,27 +609,572 @@ impl FaceDetectionModelOutput {
 
     fn from_with_threshold(
         anchors: &Vec<Anchor>,
-        regressors: &Vec<f32>,
+        regressors: &[f32],
         scores: &mut Vec<f32>,
         score_threshold: f32,
     ) -> Vec<Self> {
+        let mut res = Vec::new();
+        Self::extend_with_threshold(anchors, regressors, scores, score_threshold, &mut res);
+        res
+    }
+
+    /// same as ```from_with_threshold```, but the results are appended to ```out```
+    fn extend_with_threshold(
+        anchors: &[Anchor],
+        regressors: &[f32],
+        scores: &mut Vec<f32>,
+        score_threshold: f32,
+        out: &mut Vec<Self>,
+    ) {
         scores.sigmoid_inplace();
         let num = min(regressors.len() >> 4, scores.len());
-        let mut res = Vec::new();
         for i in 0..num {
             let score = scores[i];
             if score > score_threshold {
-                res.push(FaceDetectionModelOutput::from(
+                out.push(FaceDetectionModelOutput::from(
                     &anchors[i],
                     &regressors[(i << 4)..],
                     score,
                 ));
             }
         }
-        res
+    }
+
+    /// map the results in the letterboxed input to the original image,
+    /// see [`ImageToTensor`]
+    pub fn remove_letterbox(&mut self, padding: &LetterboxPadding) {
+        if padding.is_empty() {
+            return;
+        }
+        self.face = self.face.clone().remove_letterbox(padding);
+        for key_point in [
+            &mut self.left_eye,
+            &mut self.right_eye,
+            &mut self.nose_tip,
+            &mut self.mouse_center,
+            &mut self.left_eye_tragion,
+            &mut self.right_eye_tragion,
+        ] {
+            *key_point = key_point.clone().remove_letterbox(padding);
+        }
     }
 
     pub fn face_box(&self) -> &Box2D<f32> {
         &self.face
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inference::tflite_model::test_model::build_tflite_model;
+    use crate::preprocess::quantize_to_bytes;
+
+    const REGRESSORS_QUANTIZATION: InferenceTensorQuantization = InferenceTensorQuantization {
+        scale: 100.0 / 127.0,
+        zero_point: 128,
+    };
+    const SCORES_QUANTIZATION: InferenceTensorQuantization = InferenceTensorQuantization {
+        scale: 16.0 / 255.0,
+        zero_point: 160,
+    };
+
+    /// two faces at anchor 100 and 500
+    fn float_outputs() -> (Vec<f32>, Vec<f32>) {
+        let mut regressors = vec![0f32; 896 * 16];
+        let mut scores = vec![-10f32; 896];
+        for (anchor, score) in [(100, 3.0), (500, 4.0)] {
+            let r = &mut regressors[anchor * 16..(anchor + 1) * 16];
+            r[..4].copy_from_slice(&[3.0, -2.0, 40.0, 44.0]);
+            for (k, v) in r[4..].iter_mut().enumerate() {
+                *v = (k as f32 - 6.0) * 4.7;
+            }
+            scores[anchor] = score;
+        }
+        (regressors, scores)
+    }
+
+    fn to_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    /// the short range model with a backend which replays the outputs of ```float_outputs```
+    /// for ```n_runs``` computes
+    fn replay_graph(n_runs: usize) -> (InferenceGraph, Arc<ReplayBackend>) {
+        let (regressors, scores) = float_outputs();
+        let outputs = vec![to_bytes(&regressors), to_bytes(&scores)];
+        let backend = Arc::new(
+            (0..n_runs).fold(ReplayBackend::new(), |b, _| b.push_outputs(outputs.clone())),
+        );
+        let graph = InferenceGraphBuilder::default()
+            .backend(backend.clone())
+            .build_from_file("./assets/face_detection_short_range.tflite")
+            .unwrap();
+        (graph, backend)
+    }
+
+    /// the face detection of ```replay_graph```
+    fn replay_face_detection(n_runs: usize) -> FaceDetection {
+        FaceDetection::new_with_graph(replay_graph(n_runs).0, 0.5).unwrap()
+    }
+
+    fn tensor_info(
+        shape: Vec<u32>,
+        tp: InferenceTensorType,
+        quantization: Option<InferenceTensorQuantization>,
+    ) -> InferenceTensorInfo {
+        InferenceTensorInfo {
+            name: String::new(),
+            shape,
+            tp,
+            quantization,
+            dynamic_batch: false,
+        }
+    }
+
+    fn process(graph: InferenceGraph) -> Vec<FaceDetectionModelOutput> {
+        let img = image::open("./assets/test.jpg").unwrap();
+        FaceDetection::new_with_graph(graph, 0.5)
+            .unwrap()
+            .process(&img)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_quantized_model_matches_float_model() {
+        let (regressors, scores) = float_outputs();
+        let float_results = process(replay_graph(1).0);
+
+        // quantized model: u8 input and u8 outputs
+        let input_quantization = InferenceTensorQuantization {
+            scale: 1.0 / 128.0,
+            zero_point: 128,
+        };
+        let model = build_tflite_model(
+            &[tensor_info(
+                vec![1, 128, 128, 3],
+                InferenceTensorType::U8,
+                Some(input_quantization.clone()),
+            )],
+            &[
+                tensor_info(
+                    vec![1, 896, 16],
+                    InferenceTensorType::U8,
+                    Some(REGRESSORS_QUANTIZATION),
+                ),
+                tensor_info(
+                    vec![1, 896, 1],
+                    InferenceTensorType::U8,
+                    Some(SCORES_QUANTIZATION),
+                ),
+            ],
+        );
+        let quantize = |v: &[f32], q: &InferenceTensorQuantization| {
+            let mut out = Vec::new();
+            quantize_to_bytes(v.iter().cloned(), &InferenceTensorType::U8, q, &mut out);
+            out
+        };
+        // the outputs can only be found if the input is quantized
+        let expect_input = image::open("./assets/test.jpg")
+            .unwrap()
+            .resize(128, 128, image::imageops::FilterType::Triangle)
+            .to_rgb8()
+            .to_quantized_tensor(
+                InferenceTensorDataLayout::NHWC,
+                InferenceTensorType::U8,
+                &input_quantization,
+            );
+        let key = ReplayKey::new(&[&model], &[(0, &expect_input)]);
+        let builder = InferenceGraphBuilder::default().backend(ReplayBackend::new().with_outputs(
+            key,
+            vec![
+                quantize(&regressors, &REGRESSORS_QUANTIZATION),
+                quantize(&scores, &SCORES_QUANTIZATION),
+            ],
+        ));
+
+        // the quantized model is found by the locator
+        let dir = std::env::temp_dir().join(format!(
+            "mediapipe_wasinn_quantized_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(
+                FaceDetectionModels::ShortRangeQuantized
+                    .file_name()
+                    .unwrap(),
+            ),
+            &model,
+        )
+        .unwrap();
+        let locator = ModelLocator::new().env_var(None).search_path(&dir);
+        let face_detection = FaceDetection::new_with_locator(
+            FaceDetectionModels::ShortRangeQuantized,
+            0.5,
+            &locator,
+            builder,
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        let img = image::open("./assets/test.jpg").unwrap();
+        let quantized_results = face_detection.unwrap().process(&img).unwrap();
+
+        assert_eq!(float_results.len(), 2);
+        assert_eq!(quantized_results.len(), float_results.len());
+        for (f, q) in float_results.iter().zip(quantized_results.iter()) {
+            assert!((f.score - q.score).abs() < 1e-2);
+            assert!((f.face.p.x - q.face.p.x).abs() < 5e-3);
+            assert!((f.face.p.y - q.face.p.y).abs() < 5e-3);
+            assert!((f.face.w - q.face.w).abs() < 5e-3);
+            assert!((f.face.h - q.face.h).abs() < 5e-3);
+            assert!((f.nose_tip.x - q.nose_tip.x).abs() < 5e-3);
+            assert!((f.right_eye_tragion.y - q.right_eye_tragion.y).abs() < 5e-3);
+        }
+    }
+
+    #[test]
+    fn test_f16_model_matches_float_model() {
+        let (regressors, scores) = float_outputs();
+        let float_results = process(replay_graph(1).0);
+
+        // fp16 model: f16 input and f16 outputs
+        let model = build_tflite_model(
+            &[tensor_info(
+                vec![1, 128, 128, 3],
+                InferenceTensorType::F16,
+                None,
+            )],
+            &[
+                tensor_info(vec![1, 896, 16], InferenceTensorType::F16, None),
+                tensor_info(vec![1, 896, 1], InferenceTensorType::F16, None),
+            ],
+        );
+        let to_f16_bytes = |v: &[f32]| {
+            v.iter()
+                .flat_map(|f| f16::from_f32(*f).to_ne_bytes())
+                .collect::<Vec<u8>>()
+        };
+        // the outputs can only be found if the input is f16
+        let img = image::open("./assets/test.jpg")
+            .unwrap()
+            .resize(128, 128, image::imageops::FilterType::Triangle)
+            .to_rgb8();
+        let input = img.to_f16_tensor(InferenceTensorDataLayout::NHWC);
+        assert_eq!(input.tp(), InferenceTensorType::F16);
+        let f16_graph = InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new().with_outputs(
+                ReplayKey::new(&[&model], &[(0, &input)]),
+                vec![to_f16_bytes(&regressors), to_f16_bytes(&scores)],
+            ))
+            .build_from_bytes(model)
+            .unwrap();
+        let f16_results = process(f16_graph);
+
+        assert_eq!(float_results.len(), 2);
+        assert_eq!(f16_results.len(), float_results.len());
+        for (f, h) in float_results.iter().zip(f16_results.iter()) {
+            assert!((f.score - h.score).abs() < 1e-2);
+            assert!((f.face.p.x - h.face.p.x).abs() < 5e-3);
+            assert!((f.face.w - h.face.w).abs() < 5e-3);
+            assert!((f.nose_tip.x - h.nose_tip.x).abs() < 5e-3);
+        }
+    }
+
+    #[test]
+    fn test_process_batch() {
+        let (regressors, scores) = float_outputs();
+        let img = image::open("./assets/test.jpg").unwrap();
+        let images = [img.clone(), img.fliph()];
+
+        // fixed batch of 1: run one by one, the second image has no faces
+        let fixed_graph = InferenceGraphBuilder::default()
+            .backend(
+                ReplayBackend::new()
+                    .push_outputs(vec![to_bytes(&regressors), to_bytes(&scores)])
+                    .push_outputs(vec![to_bytes(&regressors), to_bytes(&[-10f32; 896])]),
+            )
+            .build_from_file("./assets/face_detection_short_range.tflite")
+            .unwrap();
+        let fixed_results = FaceDetection::new_with_graph(fixed_graph, 0.5)
+            .unwrap()
+            .process_batch(&images)
+            .unwrap();
+        assert_eq!(fixed_results.len(), 2);
+        assert_eq!(fixed_results[0].len(), 2);
+        assert!(fixed_results[1].is_empty());
+
+        // dynamic batch: the images are stacked and run once
+        let mut input_info = tensor_info(vec![1, 128, 128, 3], InferenceTensorType::F32, None);
+        input_info.dynamic_batch = true;
+        let model = build_tflite_model(
+            &[input_info],
+            &[
+                tensor_info(vec![1, 896, 16], InferenceTensorType::F32, None),
+                tensor_info(vec![1, 896, 1], InferenceTensorType::F32, None),
+            ],
+        );
+        let inputs = images
+            .iter()
+            .map(|img| {
+                img.resize(128, 128, image::imageops::FilterType::Triangle)
+                    .to_rgb8()
+                    .to_tensor(InferenceTensorDataLayout::NHWC)
+            })
+            .collect::<Vec<_>>();
+        let input = InferenceTensor::stack(&inputs).unwrap();
+        assert_eq!(input.shape_ref(), &[2, 128, 128, 3]);
+        let batch_regressors = [regressors.clone(), regressors].concat();
+        let batch_scores = [scores, vec![-10f32; 896]].concat();
+        let dynamic_graph = InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new().with_outputs(
+                ReplayKey::new(&[&model], &[(0, &input)]),
+                vec![to_bytes(&batch_regressors), to_bytes(&batch_scores)],
+            ))
+            .build_from_bytes(model)
+            .unwrap();
+        let dynamic_results = FaceDetection::new_with_graph(dynamic_graph, 0.5)
+            .unwrap()
+            .process_batch(&images)
+            .unwrap();
+        assert_eq!(dynamic_results.len(), 2);
+        assert!(dynamic_results[1].is_empty());
+        for (f, d) in fixed_results[0].iter().zip(dynamic_results[0].iter()) {
+            assert_eq!(f.score, d.score);
+            assert_eq!(f.face.p.x, d.face.p.x);
+            assert_eq!(f.face.w, d.face.w);
+        }
+    }
+
+    #[test]
+    fn test_process_frame_reuse_buffers() {
+        let (regressors, scores) = float_outputs();
+        let img = image::open("./assets/test.jpg").unwrap();
+        // the outputs can only be found by the input, so ```process``` and ```process_frame```
+        // must produce the same input tensor
+        let model = std::fs::read("./assets/face_detection_short_range.tflite").unwrap();
+        let mut image_to_tensor = ImageToTensor::new(128, 128);
+        let input = image_to_tensor
+            .convert(&img.to_rgb8())
+            .0
+            .to_tensor(InferenceTensorDataLayout::NHWC);
+        let graph = InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new().with_outputs(
+                ReplayKey::new(&[&model], &[(0, &input)]),
+                vec![to_bytes(&regressors), to_bytes(&scores)],
+            ))
+            .build_from_bytes(model)
+            .unwrap();
+        let expect = FaceDetection::new_with_graph(graph.clone(), 0.5)
+            .unwrap()
+            .process(&img)
+            .unwrap();
+
+        let mut processor = FaceDetection::new_with_graph(graph, 0.5)
+            .unwrap()
+            .generate_processor()
+            .unwrap();
+        // the buffers are sized from the model information
+        assert!(processor.frame.input.capacity() >= 128 * 128 * 3 * 4);
+        assert!(processor.frame.regressors.capacity() >= 896 * 16);
+
+        let frame = img.to_rgb8();
+        let mut pointers = Vec::new();
+        for _ in 0..2 {
+            let results = processor.process_frame(&frame).unwrap();
+            assert_eq!(results.len(), expect.len());
+            for (e, r) in expect.iter().zip(results) {
+                assert_eq!(e.score, r.score);
+                assert_eq!(e.face.p.x, r.face.p.x);
+            }
+            pointers.push((
+                processor.frame.input.as_ptr(),
+                processor.frame.regressors.as_ptr(),
+                processor.frame.scores.as_ptr(),
+                processor.frame.results.as_ptr(),
+            ));
+        }
+        // no buffers are reallocated for the second frame
+        assert_eq!(pointers[0], pointers[1]);
+        assert_eq!(processor.process_img(&img).unwrap().len(), expect.len());
+    }
+
+    #[test]
+    fn test_remove_letterbox() {
+        let face_detection = replay_face_detection(3);
+        let img = image::open("./assets/test.jpg").unwrap();
+        // the square image has no padding
+        let expect = face_detection.process(&img).unwrap();
+
+        // 256x128 is letterboxed to 128x64 with 32 rows of padding at the top and bottom
+        let wide = RgbImage::new(256, 128);
+        let check = |results: &[FaceDetectionModelOutput]| {
+            assert_eq!(results.len(), expect.len());
+            for (e, r) in expect.iter().zip(results) {
+                assert_eq!(r.face.p.x, e.face.p.x);
+                assert_eq!(r.face.w, e.face.w);
+                assert_eq!(r.face.p.y, (e.face.p.y - 0.25) * 2.0);
+                assert_eq!(r.face.h, e.face.h * 2.0);
+                assert_eq!(r.nose_tip.x, e.nose_tip.x);
+                assert_eq!(r.nose_tip.y, (e.nose_tip.y - 0.25) * 2.0);
+            }
+        };
+        check(
+            &face_detection
+                .process(&DynamicImage::ImageRgb8(wide.clone()))
+                .unwrap(),
+        );
+        check(
+            face_detection
+                .generate_processor()
+                .unwrap()
+                .process_frame(&wide)
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_profile_stages() {
+        let img = image::open("./assets/test.jpg").unwrap();
+        let profiler = Profiler::new();
+        let face_detection = replay_face_detection(2).profiler(profiler.clone());
+        face_detection.process(&img).unwrap();
+        face_detection
+            .generate_processor()
+            .unwrap()
+            .process_frame(&img.to_rgb8())
+            .unwrap();
+
+        let stats = profiler.stats();
+        let stages = stats.iter().map(|s| s.stage).collect::<Vec<_>>();
+        assert_eq!(
+            stages,
+            [
+                "resize",
+                "to_tensor",
+                "set_input",
+                "compute",
+                "get_output",
+                "decode",
+                "process"
+            ]
+        );
+        assert!(stats
+            .iter()
+            .all(|s| s.count == 2 || s.stage == "get_output"));
+        assert_eq!(stats[4].count, 4);
+    }
+
+    #[test]
+    fn test_warmup() {
+        let (graph, backend) = replay_graph(3);
+        let face_detection = FaceDetection::new_with_graph(graph, 0.5)
+            .unwrap()
+            .executor_pool_size(2);
+        assert!(!face_detection.is_ready());
+        assert!(face_detection.warmup(0).is_err());
+        assert!(!face_detection.is_ready());
+
+        // every executor of the pool is warmed up
+        face_detection.warmup(1).unwrap();
+        assert!(face_detection.is_ready());
+        assert_eq!(backend.remaining(), 1);
+        assert_eq!(backend.live_execution_contexts(), 2);
+        // the warmed up executors are reused
+        let img = image::open("./assets/test.jpg").unwrap();
+        assert_eq!(face_detection.process(&img).unwrap().len(), 2);
+        assert_eq!(backend.live_execution_contexts(), 2);
+
+        // the processor has its own executor
+        assert!(!face_detection.generate_processor().unwrap().is_ready());
+    }
+
+    #[test]
+    fn test_quantized_model_not_found() {
+        let err = FaceDetection::new_with_locator(
+            FaceDetectionModels::ShortRangeQuantized,
+            0.5,
+            &ModelLocator::new().env_var(None),
+            InferenceGraphBuilder::default().backend(ReplayBackend::new()),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, Error::ModelLoad { .. }));
+        assert!(err
+            .to_string()
+            .contains("face_detection_short_range_quantized.tflite"));
+    }
+
+    #[test]
+    fn test_unsupported_model() {
+        for model in [
+            FaceDetectionModels::FullRangeDense,
+            FaceDetectionModels::FullRangeSparse,
+        ] {
+            assert!(model.file_name().is_none());
+            let err = FaceDetection::new(model, 0.5).err().unwrap();
+            assert!(matches!(err, Error::Config(_)));
+            assert!(err.to_string().contains("model is not supported"));
+        }
+    }
+
+    #[test]
+    fn test_reject_mismatched_model() {
+        // a full range model has a 192x192 input
+        let model = build_tflite_model(
+            &[tensor_info(
+                vec![1, 192, 192, 3],
+                InferenceTensorType::F32,
+                None,
+            )],
+            &[
+                tensor_info(vec![1, 2304, 16], InferenceTensorType::F32, None),
+                tensor_info(vec![1, 2304, 1], InferenceTensorType::F32, None),
+            ],
+        );
+        let graph = InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new())
+            .build_from_bytes(model)
+            .unwrap();
+        let err = FaceDetection::new_with_graph(graph, 0.5).err().unwrap();
+        assert!(matches!(err, crate::Error::Config(_)));
+        assert!(err.to_string().contains("input 0 (image)"));
+    }
+
+    #[cfg(feature = "embed-face-detection-short-range")]
+    #[test]
+    fn test_embedded_model() {
+        let model = FaceDetectionModels::ShortRange.embedded_model().unwrap();
+        assert_eq!(
+            model,
+            std::fs::read("./assets/face_detection_short_range.tflite").unwrap()
+        );
+        assert!(FaceDetectionModels::FullRangeDense
+            .embedded_model()
+            .is_none());
+    }
+
+    #[cfg(feature = "embed-face-detection-short-range")]
+    #[test]
+    fn test_env_overrides_embedded_model() {
+        let builder = || InferenceGraphBuilder::default().backend(ReplayBackend::new());
+        assert!(
+            FaceDetection::new_with_builder(FaceDetectionModels::ShortRange, 0.5, builder())
+                .is_ok()
+        );
+
+        // a broken model file in the env directory is used instead of the embedded model
+        let dir = std::env::temp_dir().join(format!(
+            "mediapipe_wasinn_embed_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("face_detection_short_range.tflite"), [0u8; 4]).unwrap();
+        std::env::set_var(crate::MODEL_DIR_ENV, &dir);
+        let err = FaceDetection::new_with_builder(FaceDetectionModels::ShortRange, 0.5, builder())
+            .err()
+            .unwrap();
+        std::env::remove_var(crate::MODEL_DIR_ENV);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(err, Error::ModelHashMismatch { .. }));
+    }
+}
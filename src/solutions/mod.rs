@@ -1,3 +1,5 @@
 mod face_detection;
+mod model_spec;
 
 pub use face_detection::*;
+pub use model_spec::*;
@@ -0,0 +1,152 @@
+use crate::error::{Error, Result};
+use crate::inference::{InferenceGraph, InferenceTensorInfo};
+
+/// the shape of a tensor which a solution expects
+#[derive(Debug, Clone)]
+pub struct TensorSpec {
+    /// the description used in error messages
+    pub name: &'static str,
+    /// the batch (first dimension) is 1 for the models with dynamic batch
+    pub shape: &'static [u32],
+}
+
+/// Model IO Spec
+/// the inputs and outputs which a solution expects from its model.
+/// the tensor types are not checked, the solutions convert the quantized and fp16 tensors.
+#[derive(Debug, Clone)]
+pub struct ModelIoSpec {
+    /// the solution name used in error messages
+    pub solution: &'static str,
+    pub inputs: &'static [TensorSpec],
+    pub outputs: &'static [TensorSpec],
+}
+
+impl ModelIoSpec {
+    /// check the inputs and outputs information of the graph,
+    /// return ```Error::Config``` with the first mismatch.
+    /// a tflite model without information (corrupt or with unsupported tensor types) is an error,
+    /// the graphs which cannot be read (such as other encodings or loaded by name) are not checked.
+    pub fn validate(&self, graph: &InferenceGraph) -> Result<()> {
+        if graph.inputs().is_empty() && graph.outputs().is_empty() {
+            if graph.is_io_readable() {
+                return Err(Error::Config(format!(
+                    "{} model inputs and outputs cannot be read (corrupt or unsupported tflite)",
+                    self.solution
+                )));
+            }
+            return Ok(());
+        }
+        self.validate_tensors("input", self.inputs, graph.inputs())?;
+        self.validate_tensors("output", self.outputs, graph.outputs())
+    }
+
+    fn validate_tensors(
+        &self,
+        kind: &str,
+        specs: &[TensorSpec],
+        infos: &[InferenceTensorInfo],
+    ) -> Result<()> {
+        if specs.len() != infos.len() {
+            return Err(Error::Config(format!(
+                "{} model expects {} {}s, got {}",
+                self.solution,
+                specs.len(),
+                kind,
+                infos.len()
+            )));
+        }
+        for (index, (spec, info)) in specs.iter().zip(infos).enumerate() {
+            if spec.shape != info.shape.as_slice() {
+                return Err(Error::Config(format!(
+                    "{} model {} {} ({}) expects shape {:?}, got {:?}",
+                    self.solution, kind, index, spec.name, spec.shape, info.shape
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inference::tflite_model::test_model::build_tflite_model;
+    use crate::inference::{
+        InferenceGraphBuilder, InferenceGraphEncoding, InferenceTensorType, ReplayBackend,
+    };
+
+    const SPEC: ModelIoSpec = ModelIoSpec {
+        solution: "test",
+        inputs: &[TensorSpec {
+            name: "image",
+            shape: &[1, 4, 4, 3],
+        }],
+        outputs: &[TensorSpec {
+            name: "scores",
+            shape: &[1, 8],
+        }],
+    };
+
+    fn graph(inputs: &[Vec<u32>], outputs: &[Vec<u32>]) -> InferenceGraph {
+        let info = |shape: &Vec<u32>| InferenceTensorInfo {
+            name: String::new(),
+            shape: shape.clone(),
+            tp: InferenceTensorType::F32,
+            quantization: None,
+            dynamic_batch: false,
+        };
+        let model = build_tflite_model(
+            &inputs.iter().map(info).collect::<Vec<_>>(),
+            &outputs.iter().map(info).collect::<Vec<_>>(),
+        );
+        InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new())
+            .build_from_bytes(model)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_validate_model_io() {
+        assert!(SPEC
+            .validate(&graph(&[vec![1, 4, 4, 3]], &[vec![1, 8]]))
+            .is_ok());
+
+        let err = SPEC
+            .validate(&graph(&[vec![1, 4, 4, 3]], &[vec![1, 8], vec![1, 2]]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: test model expects 1 outputs, got 2"
+        );
+
+        let err = SPEC
+            .validate(&graph(&[vec![1, 8, 8, 3]], &[vec![1, 8]]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid config: test model input 0 (image) expects shape [1, 4, 4, 3], got [1, 8, 8, 3]"
+        );
+
+        // a tflite model without information is an error
+        let graph = InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new())
+            .build_from_bytes(vec![0])
+            .unwrap();
+        let err = SPEC.validate(&graph).unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+        assert!(err.to_string().contains("cannot be read"));
+
+        // the graphs which cannot be read are not checked
+        let graph = InferenceGraphBuilder::default()
+            .encoding(InferenceGraphEncoding::Onnx)
+            .backend(ReplayBackend::new())
+            .build_from_bytes(vec![0])
+            .unwrap();
+        assert!(SPEC.validate(&graph).is_ok());
+        let graph = InferenceGraphBuilder::default()
+            .backend(ReplayBackend::new())
+            .build_from_name("test")
+            .unwrap();
+        assert!(SPEC.validate(&graph).is_ok());
+    }
+}
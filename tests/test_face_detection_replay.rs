@@ -0,0 +1,102 @@
+use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, ReplayBackend};
+use mediapipe_wasinn_demo::{FaceDetection, FaceDetectionModels};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn f32_to_bytes(data: &[f32]) -> Vec<u8> {
+    data.iter().flat_map(|f| f.to_ne_bytes()).collect()
+}
+
+#[test]
+fn test_face_detection_replay() {
+    let assets = PathBuf::from("./assets");
+
+    // one face at anchor 0 with (w, h) = (64, 64) pixels, other anchors have low scores
+    let mut regressors = vec![0f32; 896 * 16];
+    regressors[2] = 64.0;
+    regressors[3] = 64.0;
+    regressors[4] = 12.8;
+    let mut scores = vec![-10f32; 896];
+    scores[0] = 5.0;
+
+    let backend =
+        ReplayBackend::new().push_outputs(vec![f32_to_bytes(&regressors), f32_to_bytes(&scores)]);
+    let face_detection = FaceDetection::new_with_builder(
+        FaceDetectionModels::ShortRange,
+        0.9,
+        InferenceGraphBuilder::default().backend(backend),
+    )
+    .unwrap();
+
+    let img = image::open(assets.join("test.jpg")).unwrap();
+    let results = face_detection.process(&img).unwrap();
+    assert_eq!(results.len(), 1);
+
+    // anchor 0 is at the center of the first cell in 16x16 feature map
+    let anchor_center = 0.5 / 16.0;
+    let face = results[0].face_box();
+    assert!((face.p.x - (anchor_center - 0.25)).abs() < 1e-6);
+    assert!((face.p.y - (anchor_center - 0.25)).abs() < 1e-6);
+    assert!((face.w - 0.5).abs() < 1e-6);
+    assert!((face.h - 0.5).abs() < 1e-6);
+    assert!((results[0].left_eye.x - (anchor_center + 0.1)).abs() < 1e-6);
+    assert!((results[0].score - 1.0 / (1.0 + (-5f32).exp())).abs() < 1e-6);
+}
+
+#[test]
+fn test_face_detection_processor_in_thread() {
+    let mut scores = vec![-10f32; 896];
+    scores[0] = 5.0;
+    let backend = ReplayBackend::new()
+        .push_outputs(vec![f32_to_bytes(&[0f32; 896 * 16]), f32_to_bytes(&scores)]);
+
+    // the processor owns the graph, so it can be created in a function and moved to a worker
+    let processor = FaceDetection::new_with_builder(
+        FaceDetectionModels::ShortRange,
+        0.9,
+        InferenceGraphBuilder::default().backend(backend),
+    )
+    .unwrap()
+    .generate_processor()
+    .unwrap();
+
+    let img = image::open("./assets/test.jpg").unwrap();
+    let results = std::thread::spawn(move || {
+        let mut processor = processor;
+        processor.process_img(&img).unwrap()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn test_face_detection_concurrent_process() {
+    let mut scores = vec![-10f32; 896];
+    scores[0] = 5.0;
+    let outputs = vec![f32_to_bytes(&[0f32; 896 * 16]), f32_to_bytes(&scores)];
+    let backend =
+        Arc::new((0..8).fold(ReplayBackend::new(), |b, _| b.push_outputs(outputs.clone())));
+    let face_detection = FaceDetection::new_with_builder(
+        FaceDetectionModels::ShortRange,
+        0.9,
+        InferenceGraphBuilder::default().backend(backend.clone()),
+    )
+    .unwrap()
+    .executor_pool_size(2);
+
+    let img = Arc::new(image::open("./assets/test.jpg").unwrap());
+    let workers = (0..8)
+        .map(|_| {
+            let face_detection = face_detection.clone();
+            let img = img.clone();
+            std::thread::spawn(move || face_detection.process(&img).unwrap().len())
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        assert_eq!(worker.join().unwrap(), 1);
+    }
+    // the executors are reused, at most 2 contexts are created
+    assert_eq!(backend.remaining(), 0);
+    assert!(backend.live_execution_contexts() <= 2);
+}
@@ -0,0 +1,27 @@
+use mediapipe_wasinn_demo::inference::{
+    live_graph_count, live_graph_executor_count, InferenceGraphBuilder, ReplayBackend,
+};
+
+// the live counters are global, so this test must be the only test in this file
+#[test]
+fn test_graph_release() {
+    let builder = InferenceGraphBuilder::default().backend(ReplayBackend::new());
+    assert_eq!(live_graph_count(), 0);
+
+    let graphs = [
+        builder.clone().build_from_bytes(vec![0]).unwrap(),
+        builder.build_from_bytes(vec![1]).unwrap(),
+    ];
+    let executors = [
+        graphs[0].new_graph_executor().unwrap(),
+        graphs[1].new_graph_executor().unwrap(),
+        graphs[1].new_graph_executor().unwrap(),
+    ];
+    assert_eq!(live_graph_count(), 2);
+    assert_eq!(live_graph_executor_count(), 3);
+
+    drop(executors);
+    assert_eq!(live_graph_executor_count(), 0);
+    drop(graphs);
+    assert_eq!(live_graph_count(), 0);
+}
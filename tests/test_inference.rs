@@ -1,3 +1,6 @@
+// this test uses the default wasi-nn backend, so it can only run in the wasi-nn host
+#![cfg(target_arch = "wasm32")]
+
 use mediapipe_wasinn_demo::inference::{InferenceGraphBuilder, InferenceTensorDataLayout};
 use mediapipe_wasinn_demo::preprocess::ToTensor;
 use std::path::PathBuf;
@@ -22,13 +25,12 @@ fn test_inference() {
     // do inference
     graph_exec.set_inputs_and_run([(0, input)]).unwrap();
 
-    const REGRESSORS_SIZE: u32 = 1 * 896 * 16;
-    const SCORES_SIZE: u32 = 1 * 896 * 1;
-
     // get output
-    let regressors = graph_exec.get_output_f32(0, REGRESSORS_SIZE).unwrap();
-    let scores = graph_exec.get_output_f32(1, SCORES_SIZE).unwrap();
+    let regressors = graph_exec.get_output::<f32>(0).unwrap();
+    let scores = graph_exec.get_output::<f32>(1).unwrap();
 
-    assert_eq!(regressors.len(), REGRESSORS_SIZE as usize);
-    assert_eq!(scores.len(), SCORES_SIZE as usize);
+    assert_eq!(regressors.shape_ref(), &[1, 896, 16]);
+    assert_eq!(scores.shape_ref(), &[1, 896, 1]);
+    assert_eq!(regressors.to_vec::<f32>().unwrap().len(), 896 * 16);
+    assert_eq!(scores.to_vec::<f32>().unwrap().len(), 896);
 }